@@ -8,7 +8,7 @@ use std::{
     path::{Path, PathBuf},
 };
 use thiserror::Error;
-use ty::{LayoutChecker, TyChecker};
+use ty::{LayoutChecker, ServiceChecker, TyChecker};
 
 #[derive(Debug, Clone)]
 pub enum Expr {
@@ -17,6 +17,9 @@ pub enum Expr {
     Service(Service),
     Bus(Bus),
     Extern(Extern),
+    Alias(Alias),
+    Newtype(Newtype),
+    Flags(Flags),
 }
 
 impl Expr {
@@ -27,6 +30,9 @@ impl Expr {
             Expr::Bus(b) => &b.ty_def,
             Expr::Service(s) => &s.ty_def,
             Expr::Extern(e) => &e.ty_def,
+            Expr::Alias(a) => &a.ty_def,
+            Expr::Newtype(n) => &n.ty_def,
+            Expr::Flags(f) => &f.ty_def,
         }
     }
 
@@ -42,41 +48,106 @@ impl Expr {
                 .iter_mut()
                 .flat_map(|v| Some((v.name.clone(), v.ty.as_mut()?)))
                 .collect(),
-            Expr::Service(s) => s
-                .methods
-                .iter_mut()
-                .flat_map(|m| {
-                    [
-                        ("arg".to_string(), &mut m.arg_ty),
-                        ("ret".to_string(), &mut m.return_ty),
-                    ]
-                })
-                .collect(),
+            Expr::Service(s) => {
+                let mut tys: Vec<(String, &mut Ty)> = s
+                    .ancestors
+                    .iter_mut()
+                    .flat_map(|a| a.generic_args.iter_mut())
+                    .flat_map(|a| match a {
+                        GenericArg::Ty(ty) => Some(("base".to_string(), ty)),
+                        GenericArg::Const(_) => None,
+                    })
+                    .collect();
+                if let Some(header) = &mut s.header {
+                    tys.push(("header".to_string(), header));
+                }
+                for m in s.methods.iter_mut() {
+                    tys.push(("arg".to_string(), &mut m.arg_ty));
+                    tys.push(("ret".to_string(), &mut m.return_ty));
+                    if let Some(base) = &mut m.base {
+                        tys.extend(base.generic_args.iter_mut().flat_map(|a| match a {
+                            GenericArg::Ty(ty) => Some(("base".to_string(), ty)),
+                            GenericArg::Const(_) => None,
+                        }));
+                    }
+                }
+                for e in s.events.iter_mut() {
+                    tys.push(("event".to_string(), &mut e.ty));
+                }
+                tys
+            }
             Expr::Bus(b) => b
                 .msgs
                 .iter_mut()
                 .map(|m| ("arg".to_string(), &mut m.ty))
                 .collect(),
             Expr::Extern(_) => vec![],
+            Expr::Alias(a) => vec![("alias".to_string(), &mut a.ty)],
+            Expr::Newtype(n) => vec![("newtype".to_string(), &mut n.ty)],
+            Expr::Flags(f) => vec![("flags".to_string(), &mut f.ty)],
         }
     }
 }
 
 peg::parser! {
-    pub grammar piton_parser() for str {
+    pub grammar piton_parser(endian: Endian) for str {
         rule ty() -> Ty
-            = "u8" { Ty::U8 }
-            / "u16" { Ty::U16 }
-            / "u32" { Ty::U32 }
-            / "u64" { Ty::U64 }
+            = ty:base_ty() optional:"?"? {
+                match optional {
+                    Some(_) => Ty::Option(Box::new(ty)),
+                    None => ty,
+                }
+            }
+
+        rule base_ty() -> Ty
+            = "nonzero" _ ty:base_ty() { Ty::NonZero(Box::new(ty)) }
+            / "option" _ "<" _ ty:ty() _ ">" { Ty::Option(Box::new(ty)) }
+            / "u128be" { Ty::U128Be }
+            / "i128be" { Ty::I128Be }
+            / "u128le" { Ty::U128 }
+            / "i128le" { Ty::I128 }
+            / "u128" { endian.apply(Ty::U128) }
+            / "i128" { endian.apply(Ty::I128) }
+            / "charbe" { Ty::CharBe }
+            / "charle" { Ty::Char }
+            / "char" { endian.apply(Ty::Char) }
+            / "u8" { Ty::U8 }
+            / "u16be" { Ty::U16Be }
+            / "u32be" { Ty::U32Be }
+            / "u64be" { Ty::U64Be }
+            / "i16be" { Ty::I16Be }
+            / "i32be" { Ty::I32Be }
+            / "i64be" { Ty::I64Be }
+            / "u16le" { Ty::U16 }
+            / "u32le" { Ty::U32 }
+            / "u64le" { Ty::U64 }
+            / "i16le" { Ty::I16 }
+            / "i32le" { Ty::I32 }
+            / "i64le" { Ty::I64 }
+            / "u16" { endian.apply(Ty::U16) }
+            / "u32" { endian.apply(Ty::U32) }
+            / "u64" { endian.apply(Ty::U64) }
             / "i8" { Ty::I8 }
-            / "i16" { Ty::I16 }
-            / "i32" { Ty::I32 }
-            / "i64" { Ty::I64 }
-            / "f32" { Ty::F32 }
-            / "f64" { Ty::F64 }
+            / "i16" { endian.apply(Ty::I16) }
+            / "i32" { endian.apply(Ty::I32) }
+            / "i64" { endian.apply(Ty::I64) }
+            / "f32be" { Ty::F32Be }
+            / "f64be" { Ty::F64Be }
+            / "f32le" { Ty::F32 }
+            / "f64le" { Ty::F64 }
+            / "f32" { endian.apply(Ty::F32) }
+            / "f64" { endian.apply(Ty::F64) }
             / "bool" { Ty::Bool }
+            / "string" _ "<" _ len:uint() _ ">" { Ty::String(len) }
+            / "string" { Ty::Str }
+            / "bytes" { Ty::Bytes }
+            / "vec" _ "<" _ ty:ty() _ "," _ len:uint() _ ">" { Ty::Vec { ty: Box::new(ty), len } }
+            / "map" _ "<" _ key:ty() _ "," _ value:ty() _ "," _ len:uint() _ ">" {
+                Ty::Map { key: Box::new(key), value: Box::new(value), len }
+            }
+            / "set" _ "<" _ ty:ty() _ "," _ len:uint() _ ">" { Ty::Set { ty: Box::new(ty), len } }
             / "[" _ ty:ty() ";" _ len:uint() _ "]" { Ty::Array { ty: Box::new(ty), len } }
+            / "[" _ ty:ty() _ "]" { Ty::Slice(Box::new(ty)) }
             / name:symbol() generic_args:generic_args()? {
                 Ty::Unresolved {
                     name,
@@ -98,11 +169,12 @@ peg::parser! {
                         generic_tys: generic_tys.unwrap_or_default()
                     },
                     fields,
+                    bitfields: vec![],
                 }
             }
 
         rule field() -> Field
-            = name:symbol() ":" _ ty:ty() { Field { name , ty }}
+            = name:symbol() ":" _ ty:ty() bits:(_ ":" _ bits:uint() { bits })? { Field { name, ty, bits }}
 
         rule enum_def() -> Enum
             = "enum" _ name:symbol() generic_tys:generic_tys()? _ "{" _ variants:(variant() ** ("," _)) _ "}" {
@@ -122,15 +194,48 @@ peg::parser! {
             = "(" ty:ty() ")" { ty }
 
         rule service_def() -> Service
-            = "service" _ ty_def:ty_def() _ "{" _ methods:(method() ** ",") _ "}" {
+            = "service" _ ty_def:ty_def() bases:service_bases()? _ "{" _ header:service_header()? items:(service_item() ** ("," _)) _ "}" {
+                let mut methods = vec![];
+                let mut events = vec![];
+                for item in items {
+                    match item {
+                        ServiceItem::Method(m) => methods.push(m),
+                        ServiceItem::Event(e) => events.push(e),
+                    }
+                }
                 Service {
                     ty_def,
-                    methods
+                    bases: bases.unwrap_or_default(),
+                    ancestors: vec![],
+                    header,
+                    methods,
+                    events
+                }
+            }
+
+        rule service_item() -> ServiceItem
+            = m:method() { ServiceItem::Method(m) }
+            / e:event() { ServiceItem::Event(e) }
+
+        rule service_header() -> Ty
+            = "header" _ ty:ty() _ ";" _ { ty }
+
+        rule service_bases() -> Vec<ServiceRef>
+            = _ ":" _ bases:(service_ref() ++ (_ "+" _)) { bases }
+
+        rule service_ref() -> ServiceRef
+            = name:symbol() generic_args:generic_args()? {
+                ServiceRef {
+                    name,
+                    generic_args: generic_args.unwrap_or_default()
                 }
             }
 
         rule method() -> Method
-            = "method" _ name:symbol() _ "(" arg_ty:ty() ")" _ "->" _ return_ty:ty() { Method { name, arg_ty, return_ty }}
+            = "method" _ name:symbol() _ "(" arg_ty:ty() ")" _ "->" _ return_ty:ty() { Method { name, arg_ty, return_ty, base: None }}
+
+        rule event() -> Msg
+            = "event" _ name:symbol() _ "(" ty:ty() ")" { Msg { name, ty } }
 
         rule bus_def() -> Bus
             = "bus" _ ty_def:ty_def() _ "{" _ msgs:(msg() ** (";" _ )) _ "}" {
@@ -173,6 +278,36 @@ peg::parser! {
                 }
             }
 
+        rule alias_def() -> Alias
+            = "type" _ ty_def:ty_def() _ "=" _ ty:ty() _ ";" { Alias { ty_def, ty } }
+
+        rule newtype_def() -> Newtype
+            = "newtype" _ name:symbol() _ "(" _ ty:ty() _ ")" _ ";" {
+                Newtype {
+                    ty_def: TyDef {
+                        name,
+                        generic_tys: vec![]
+                    },
+                    ty
+                }
+            }
+
+        rule flags_def() -> Flags
+            = strict:("strict" _)? "flags" _ name:symbol() _ ":" _ ty:ty() _ "{" _ flags:(flag() ** ("," _)) _ "}" {
+                Flags {
+                    ty_def: TyDef {
+                        name,
+                        generic_tys: vec![]
+                    },
+                    ty,
+                    strict: strict.is_some(),
+                    flags,
+                }
+            }
+
+        rule flag() -> Flag
+            = name:symbol() _ "=" _ bit:uint() { Flag { name, bit } }
+
         rule concrete() -> (String, Vec<TemplateToken>)
             = "concrete" _ lang:symbol() _ "=" _ im:template() { (lang, im) }
 
@@ -202,12 +337,21 @@ peg::parser! {
             / s:service_def() { Expr::Service(s) }
             / b:bus_def() { Expr::Bus(b) }
             / e:extern_def() { Expr::Extern(e) }
+            / a:alias_def() { Expr::Alias(a) }
+            / n:newtype_def() { Expr::Newtype(n) }
+            / f:flags_def() { Expr::Flags(f) }
 
 
         rule _() = quiet!{[' ' | '\n' | '\t']*}
 
+        rule endian_attr() -> Endian
+            = "#![endian(" _ endian:("big" { Endian::Big } / "little" { Endian::Little }) _ ")]" { endian }
+
+        pub rule endian() -> Endian
+            = _ endian:endian_attr()? [_]* { endian.unwrap_or_default() }
+
         pub rule exprs() -> Vec<Expr>
-            = exprs:(expr:expr() ** _) _ { exprs }
+            = _ (endian_attr() _)? exprs:(expr:expr() ** _) _ { exprs }
     }
 }
 
@@ -255,6 +399,36 @@ pub struct Extern {
     concrete_impls: HashMap<String, Vec<TemplateToken>>,
 }
 
+/// A transparent alias for another type, replaced by that type during type checking
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Alias {
+    ty_def: TyDef,
+    ty: Ty,
+}
+
+/// A distinct type with the same wire representation as the type it wraps
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Newtype {
+    ty_def: TyDef,
+    ty: Ty,
+}
+
+/// A set of named bits packed into an unsigned integer
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Flags {
+    ty_def: TyDef,
+    ty: Ty,
+    /// Whether validation rejects bits that don't belong to any flag
+    strict: bool,
+    flags: Vec<Flag>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Flag {
+    name: String,
+    bit: usize,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TyDef {
     name: String,
@@ -265,12 +439,47 @@ pub struct TyDef {
 pub struct Struct {
     ty_def: TyDef,
     fields: Vec<Field>,
+    /// Integer fields that bitfield members have been packed into
+    bitfields: Vec<BitfieldUnit>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Field {
     name: String,
     ty: Ty,
+    /// The width in bits of a bitfield member
+    bits: Option<usize>,
+}
+
+/// A run of bitfield members packed into a single integer field
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BitfieldUnit {
+    /// The name of the field holding the packed integer
+    field: String,
+    /// The name of the type generated for the packed integer
+    ty_name: String,
+    /// The unsigned integer type backing the unit
+    ty: Ty,
+    members: Vec<BitfieldMember>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BitfieldMember {
+    name: String,
+    ty: BitfieldTy,
+    offset: usize,
+    width: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BitfieldTy {
+    Bool,
+    Uint(Ty),
+    /// A fieldless enum, and the names of its variants in discriminant order
+    Enum {
+        name: String,
+        variants: Vec<String>,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -285,10 +494,30 @@ struct Variant {
     ty: Option<Ty>,
 }
 
+enum ServiceItem {
+    Method(Method),
+    Event(Msg),
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Service {
     pub ty_def: TyDef,
+    /// The services this service directly extends, as written in the source
+    pub bases: Vec<ServiceRef>,
+    /// Every service this service extends, directly or not, with generic args
+    /// expressed in terms of this service. Filled in when services are resolved
+    pub ancestors: Vec<ServiceRef>,
+    /// Sent ahead of the argument of every request. Inherited from the bases if they declare one
+    pub header: Option<Ty>,
     pub methods: Vec<Method>,
+    /// Pushed by the server to its clients. Unlike methods, events aren't inherited
+    pub events: Vec<Msg>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ServiceRef {
+    pub name: String,
+    pub generic_args: Vec<GenericArg>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -296,6 +525,8 @@ pub struct Method {
     pub name: String,
     pub arg_ty: Ty,
     pub return_ty: Ty,
+    /// The service this method was inherited from, if any
+    pub base: Option<ServiceRef>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -312,10 +543,12 @@ pub struct Msg {
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Ty {
+    U128,
     U64,
     U32,
     U16,
     U8,
+    I128,
     I64,
     I32,
     I16,
@@ -323,6 +556,46 @@ pub enum Ty {
     F32,
     F64,
     Bool,
+    Char,
+    U128Be,
+    U64Be,
+    U32Be,
+    U16Be,
+    I128Be,
+    I64Be,
+    I32Be,
+    I16Be,
+    F32Be,
+    F64Be,
+    CharBe,
+    /// An integer that must not be zero
+    NonZero(Box<Ty>),
+    /// A UTF-8 string that can hold up to the given number of bytes
+    String(usize),
+    /// A variable-length UTF-8 string, stored in the trailing region of the message
+    Str,
+    /// A variable-length run of bytes, stored in the trailing region of the message
+    Bytes,
+    /// A variable-length run of elements, stored in the trailing region of the message
+    Slice(Box<Ty>),
+    /// A value that may not be present
+    Option(Box<Ty>),
+    /// A vector that can hold up to `len` elements
+    Vec {
+        ty: Box<Ty>,
+        len: usize,
+    },
+    /// A map that can hold up to `len` entries, sorted by key
+    Map {
+        key: Box<Ty>,
+        value: Box<Ty>,
+        len: usize,
+    },
+    /// A set that can hold up to `len` items, kept sorted
+    Set {
+        ty: Box<Ty>,
+        len: usize,
+    },
     Array {
         ty: Box<Ty>,
         len: usize,
@@ -334,6 +607,34 @@ pub enum Ty {
     Extern(String),
 }
 
+/// The byte order integers, floats and chars without an explicit `le` or `be` suffix are encoded with,
+/// set for a whole schema with `#![endian(big)]`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endian {
+    pub(crate) fn apply(self, ty: Ty) -> Ty {
+        match (self, ty) {
+            (Endian::Big, Ty::U128) => Ty::U128Be,
+            (Endian::Big, Ty::U64) => Ty::U64Be,
+            (Endian::Big, Ty::U32) => Ty::U32Be,
+            (Endian::Big, Ty::U16) => Ty::U16Be,
+            (Endian::Big, Ty::I128) => Ty::I128Be,
+            (Endian::Big, Ty::I64) => Ty::I64Be,
+            (Endian::Big, Ty::I32) => Ty::I32Be,
+            (Endian::Big, Ty::I16) => Ty::I16Be,
+            (Endian::Big, Ty::F32) => Ty::F32Be,
+            (Endian::Big, Ty::F64) => Ty::F64Be,
+            (Endian::Big, Ty::Char) => Ty::CharBe,
+            (_, ty) => ty,
+        }
+    }
+}
+
 pub trait TypeGenerator {
     fn generate(&self, doc: &[Expr]) -> miette::Result<String> {
         let strings = doc
@@ -341,6 +642,9 @@ pub trait TypeGenerator {
             .map(|e| match e {
                 Expr::Struct(s) => self.generate_struct(s),
                 Expr::Enum(e) => self.generate_enum(e),
+                Expr::Alias(a) => self.generate_alias(a),
+                Expr::Newtype(n) => self.generate_newtype(n),
+                Expr::Flags(f) => self.generate_flags(f),
                 _ => Ok(String::default()),
             })
             .collect::<miette::Result<Vec<_>>>()?;
@@ -352,6 +656,9 @@ pub trait TypeGenerator {
     }
     fn generate_struct(&self, s: &Struct) -> miette::Result<String>;
     fn generate_enum(&self, e: &Enum) -> miette::Result<String>;
+    fn generate_alias(&self, a: &Alias) -> miette::Result<String>;
+    fn generate_newtype(&self, n: &Newtype) -> miette::Result<String>;
+    fn generate_flags(&self, f: &Flags) -> miette::Result<String>;
 }
 
 pub trait ServiceGenerator {
@@ -401,6 +708,8 @@ pub struct RustBuilder {
     types: bool,
     server: bool,
     client: bool,
+    async_: bool,
+    deferred: bool,
 }
 
 impl RustBuilder {
@@ -419,10 +728,25 @@ impl RustBuilder {
         self
     }
 
+    /// Generates servers and clients for the async transport traits, like `piton::AsyncServiceRx`,
+    /// with async handlers
+    pub fn async_(mut self) -> Self {
+        self.async_ = true;
+        self
+    }
+
+    /// Generates servers for `piton::DeferRx` transports, whose handlers are given the reply and
+    /// can send it later, after returning
+    pub fn deferred(mut self) -> Self {
+        self.deferred = true;
+        self
+    }
+
     pub fn build(self, path: impl AsRef<Path>) -> miette::Result<()> {
         let path = path.as_ref();
         let doc = std::fs::read_to_string(path).into_diagnostic()?;
-        let mut exprs = piton_parser::exprs(&doc).map_err(|err| ParseError {
+        let endian = piton_parser::endian(&doc, Endian::Little).unwrap_or_default();
+        let mut exprs = piton_parser::exprs(&doc, endian).map_err(|err| ParseError {
             src: NamedSource::new(
                 path.file_name()
                     .and_then(|s| s.to_str())
@@ -433,21 +757,32 @@ impl RustBuilder {
             msg: format!("expected {}", err.expected),
         })?;
 
-        let mut checker = TyChecker::default();
+        let mut service_checker = ServiceChecker::default();
         for expr in &exprs {
-            checker.visit_expr(expr);
+            service_checker.visit_expr(expr);
         }
         for expr in &mut exprs {
-            checker.resolve_expr(expr)?;
+            service_checker.resolve_expr(expr)?;
+        }
+
+        let mut ty_checker = TyChecker::new(endian);
+        for expr in &exprs {
+            ty_checker.visit_expr(expr);
+        }
+        for expr in &mut exprs {
+            ty_checker.resolve_expr(expr)?;
         }
         for expr in &mut exprs {
             let mut checker = LayoutChecker::default();
             if let Expr::Struct(ref mut s) = expr {
                 let name = s.ty_def.name.clone();
                 println!("struct {name}");
+                ty_checker
+                    .pack_bitfields(s)
+                    .wrap_err(format!("{name} error"))?;
                 for field in &s.fields {
                     checker
-                        .next_field(field.ty.layout())
+                        .next_field(ty_checker.layout(&field.ty))
                         .wrap_err(format!("{name}, {}", field.name))?;
                 }
                 let final_pad = checker.final_pad();
@@ -455,6 +790,7 @@ impl RustBuilder {
                     s.fields.push(Field {
                         name: "_pad".to_string(),
                         ty: Ty::Extern(format!("piton::ZeroPad<{}>", final_pad)),
+                        bits: None,
                     })
                 }
             }
@@ -465,12 +801,25 @@ impl RustBuilder {
             o += &rust::TypeGenerator.generate(&exprs)?;
         }
         if self.server {
-            o += &rust::ServiceGenerator.generate(&exprs)?;
-            o += &rust::BusRxGenerator.generate(&exprs)?;
+            o += &rust::ServiceGenerator {
+                async_: self.async_,
+                deferred: self.deferred,
+            }
+            .generate(&exprs)?;
+            o += &rust::BusRxGenerator {
+                async_: self.async_,
+            }
+            .generate(&exprs)?;
         }
         if self.client {
-            o += &rust::ClientGenerator.generate(&exprs)?;
-            o += &rust::BusTxGenerator.generate(&exprs)?;
+            o += &rust::ClientGenerator {
+                async_: self.async_,
+            }
+            .generate(&exprs)?;
+            o += &rust::BusTxGenerator {
+                async_: self.async_,
+            }
+            .generate(&exprs)?;
         }
         if self.client || self.server {
             o += &rust::ReqGenerator.generate(&exprs)?;
@@ -19,14 +19,58 @@ impl super::TypeGenerator for TypeGenerator {
                 <$(for t in &s.ty_def.generic_tys => $(t.to_rust()))>
             }
         };
+        let field_ty =
+            |field: &crate::Field| match s.bitfields.iter().find(|u| u.field == field.name) {
+                Some(unit) => unit.ty_name.clone(),
+                None => ty_to_rust(&field.ty),
+            };
+        let accessors: Vec<rust::Tokens> = s
+            .bitfields
+            .iter()
+            .flat_map(|unit| unit.members.iter().map(move |member| (unit, member)))
+            .map(|(unit, member)| {
+                let member_ty = bitfield_member_ty(&member.ty);
+                quote! {
+                    pub fn $(&member.name)(&self) -> $(&member_ty) {
+                        self.$(&unit.field).$(&member.name)()
+                    }
+
+                    pub fn set_$(&member.name)(&mut self, value: $(&member_ty)) {
+                        self.$(&unit.field).set_$(&member.name)(value)
+                    }
+                }
+            })
+            .collect();
+        let units = s
+            .bitfields
+            .iter()
+            .map(generate_bitfield_unit)
+            .collect::<miette::Result<Vec<_>>>()?;
         let tokens: rust::Tokens = quote! {
-            #[derive(bytecheck::CheckBytes, Clone, Debug, PartialEq, Eq, Default)]
+            #[derive(piton::Yule, Clone, Debug, PartialEq, Eq, Default)]
             #[repr(C)]
             pub struct $(&s.ty_def.name) $(&generic_args) {
-                $(for field in &s.fields => pub $(&field.name): $(ty_to_rust(&field.ty)),)
+                $(for field in &s.fields => pub $(&field.name): $(field_ty(field)),)
             }
 
-            unsafe impl<$(generic_tys)> piton::Yule for $(&s.ty_def.name) $(generic_args) {}
+            impl<$(&generic_tys)> bytecheck::CheckBytes<piton::types::TailContext> for $(&s.ty_def.name) $(&generic_args) {
+                type Error = piton::PathCheckError;
+
+                unsafe fn check_bytes<'a>(value: *const Self, context: &mut piton::types::TailContext) -> Result<&'a Self, Self::Error> {
+                    $(for field in &s.fields {
+                        $(check_field(&field_ty(field), quote!(core::ptr::addr_of!((*value).$(&field.name))), quote!(piton::PathSegment::Field($(quoted(&field.name))))))
+                    })
+                    Ok(&*value)
+                }
+            }
+
+            $(if !accessors.is_empty() {
+                impl<$(&generic_tys)> $(&s.ty_def.name) $(&generic_args) {
+                    $(for a in accessors => $(a))
+                }
+            })
+
+            $(for unit in units => $(unit))
         };
         tokens.to_file_string().into_diagnostic()
     }
@@ -68,23 +112,516 @@ impl super::TypeGenerator for TypeGenerator {
         let generic_tys =
             quote! { $(for t in &e.ty_def.generic_tys => $(t.to_rust()): piton::Yule + 'static,) };
 
+        let check_arms: Vec<rust::Tokens> = e
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(tag, var)| match &var.ty {
+                Some(ty) => {
+                    let ty = ty_to_rust(ty);
+                    let name = var.name.to_case(Case::Pascal);
+                    quote! {
+                        $tag => {
+                            let variant = value as *const Variant<$(&ty)>;
+                            $(check_field(&ty, quote!(core::ptr::addr_of!((*variant).1)), quote!(piton::PathSegment::Field($(quoted(name))))))
+                        }
+                    }
+                }
+                None => quote!($tag => {}),
+            })
+            .collect();
+
         let tokens: rust::Tokens = quote! {
-            #[derive(bytecheck::CheckBytes, Clone, Debug)]
+            #[derive(Clone, Debug, PartialEq, Eq)]
             #[repr(u8)]
             pub enum $(&e.ty_def.name)$(&generic_args) {
                 $(for t in vars => $(t))
             }
 
+            impl<$(&generic_tys)> bytecheck::CheckBytes<piton::types::TailContext> for $(&e.ty_def.name)$(&generic_args) {
+                type Error = piton::PathCheckError;
+
+                unsafe fn check_bytes<'a>(value: *const Self, context: &mut piton::types::TailContext) -> Result<&'a Self, Self::Error> {
+                    #[allow(dead_code)]
+                    #[repr(C)]
+                    struct Variant<T>(u8, T);
+
+                    // Each variant is laid out like a `Variant` of its payload, so the tag comes first
+                    #[allow(unused_variables)]
+                    match *(value as *const u8) {
+                        $(for arm in check_arms => $(arm))
+                        _ => return Err(piton::PathCheckError::new()),
+                    }
+                    Ok(&*value)
+                }
+            }
+
             impl<$(&generic_tys)> core::default::Default for $(&e.ty_def.name)$(&generic_args) {
                 fn default() -> Self {
                     $(default_arg)
                 }
             }
 
-            unsafe impl<$(generic_tys)> piton::Yule for $(&e.ty_def.name) $(generic_args) {}
+            unsafe impl<$(generic_tys)> piton::Yule for $(&e.ty_def.name) $(generic_args) {
+                $(enum_layout_assert(e.variants.iter().flat_map(|v| v.ty.as_ref())))
+            }
+        };
+        tokens.to_file_string().into_diagnostic()
+    }
+
+    fn generate_alias(&self, a: &crate::Alias) -> miette::Result<String> {
+        let generic_args: rust::Tokens = if a.ty_def.generic_tys.is_empty() {
+            quote! {}
+        } else {
+            quote! {
+                <$(for t in &a.ty_def.generic_tys => $(t.to_rust()))>
+            }
+        };
+        let tokens: rust::Tokens = quote! {
+            pub type $(&a.ty_def.name)$(generic_args) = $(ty_to_rust(&a.ty));
         };
         tokens.to_file_string().into_diagnostic()
     }
+
+    fn generate_flags(&self, f: &crate::Flags) -> miette::Result<String> {
+        let name = &f.ty_def.name;
+        let (native, storage, bits, from_bits) = Self::flags_storage(&f.ty)?;
+        let all = f.flags.iter().fold(0u64, |all, flag| all | 1 << flag.bit);
+        let consts: Vec<rust::Tokens> = f
+            .flags
+            .iter()
+            .map(|flag| {
+                quote! {
+                    pub const $(flag.name.to_case(Case::UpperSnake)): Self = Self::from_bits_retain(1 << $(flag.bit));
+                }
+            })
+            .collect();
+        let check: rust::Tokens = if f.strict {
+            quote! {
+                impl<C: ?Sized> bytecheck::CheckBytes<C> for $name {
+                    type Error = piton::types::UnknownFlagsError;
+
+                    unsafe fn check_bytes<'a>(value: *const Self, _: &mut C) -> Result<&'a Self, Self::Error> {
+                        let value = &*value;
+                        let unknown = value.bits() & !Self::all().bits();
+                        if unknown != 0 {
+                            return Err(piton::types::UnknownFlagsError { bits: unknown.into() });
+                        }
+                        Ok(value)
+                    }
+                }
+            }
+        } else {
+            quote! {
+                impl<C: ?Sized> bytecheck::CheckBytes<C> for $name {
+                    type Error = core::convert::Infallible;
+
+                    unsafe fn check_bytes<'a>(value: *const Self, _: &mut C) -> Result<&'a Self, Self::Error> {
+                        Ok(&*value)
+                    }
+                }
+            }
+        };
+        let ops: Vec<rust::Tokens> = [
+            ("BitOr", "bitor", "BitOrAssign", "bitor_assign", "|"),
+            ("BitAnd", "bitand", "BitAndAssign", "bitand_assign", "&"),
+            ("BitXor", "bitxor", "BitXorAssign", "bitxor_assign", "^"),
+        ]
+        .into_iter()
+        .map(|(op, op_fn, assign, assign_fn, sym)| {
+            quote! {
+                impl core::ops::$op for $name {
+                    type Output = Self;
+
+                    fn $op_fn(self, rhs: Self) -> Self {
+                        Self::from_bits_retain(self.bits() $sym rhs.bits())
+                    }
+                }
+
+                impl core::ops::$assign for $name {
+                    fn $assign_fn(&mut self, rhs: Self) {
+                        *self = *self $sym rhs;
+                    }
+                }
+            }
+        })
+        .collect();
+
+        let tokens: rust::Tokens = quote! {
+            #[derive(piton::Yule, Clone, Copy, PartialEq, Eq, Hash, Default)]
+            #[repr(transparent)]
+            pub struct $name($(&storage));
+
+            impl $name {
+                $(for c in consts => $(c))
+
+                pub const FLAGS: &'static [(&'static str, Self)] = &[
+                    $(for flag in &f.flags => ($(quoted(&flag.name)), Self::$(flag.name.to_case(Case::UpperSnake))),)
+                ];
+
+                pub const fn empty() -> Self {
+                    Self::from_bits_retain(0)
+                }
+
+                pub const fn all() -> Self {
+                    Self::from_bits_retain($(format!("{:#x}", all)))
+                }
+
+                pub const fn from_bits_retain(bits: $native) -> Self {
+                    $(&from_bits)
+                }
+
+                pub fn from_bits(bits: $native) -> Option<Self> {
+                    (bits & !Self::all().bits() == 0).then(|| Self::from_bits_retain(bits))
+                }
+
+                pub fn from_bits_truncate(bits: $native) -> Self {
+                    Self::from_bits_retain(bits & Self::all().bits())
+                }
+
+                pub fn bits(&self) -> $native {
+                    $(&bits)
+                }
+
+                pub fn is_empty(&self) -> bool {
+                    self.bits() == 0
+                }
+
+                pub fn is_all(&self) -> bool {
+                    self.bits() & Self::all().bits() == Self::all().bits()
+                }
+
+                pub fn contains(&self, other: Self) -> bool {
+                    self.bits() & other.bits() == other.bits()
+                }
+
+                pub fn intersects(&self, other: Self) -> bool {
+                    self.bits() & other.bits() != 0
+                }
+
+                pub fn insert(&mut self, other: Self) {
+                    *self = Self::from_bits_retain(self.bits() | other.bits());
+                }
+
+                pub fn remove(&mut self, other: Self) {
+                    *self = Self::from_bits_retain(self.bits() & !other.bits());
+                }
+
+                pub fn toggle(&mut self, other: Self) {
+                    *self = Self::from_bits_retain(self.bits() ^ other.bits());
+                }
+
+                pub fn set(&mut self, other: Self, value: bool) {
+                    if value {
+                        self.insert(other)
+                    } else {
+                        self.remove(other)
+                    }
+                }
+
+                /// Iterates over every named flag that is set
+                pub fn iter(&self) -> impl Iterator<Item = Self> {
+                    let this = *self;
+                    Self::FLAGS.iter().map(|(_, flag)| *flag).filter(move |flag| this.contains(*flag))
+                }
+            }
+
+            $(for op in ops => $(op))
+
+            impl core::ops::Not for $name {
+                type Output = Self;
+
+                fn not(self) -> Self {
+                    Self::from_bits_truncate(!self.bits())
+                }
+            }
+
+            impl core::fmt::Debug for $name {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    f.write_str($(quoted(format!("{}(", name))))?;
+                    let mut first = true;
+                    for (name, flag) in Self::FLAGS {
+                        if self.contains(*flag) {
+                            if !first {
+                                f.write_str(" | ")?;
+                            }
+                            first = false;
+                            f.write_str(name)?;
+                        }
+                    }
+                    let unknown = self.bits() & !Self::all().bits();
+                    if unknown != 0 {
+                        if !first {
+                            f.write_str(" | ")?;
+                        }
+                        write!(f, "{:#x}", unknown)?;
+                    }
+                    f.write_str(")")
+                }
+            }
+
+            $(check)
+        };
+        tokens.to_file_string().into_diagnostic()
+    }
+
+    fn generate_newtype(&self, n: &crate::Newtype) -> miette::Result<String> {
+        let name = &n.ty_def.name;
+        let inner = ty_to_rust(&n.ty);
+        let tokens: rust::Tokens = quote! {
+            #[derive(piton::Yule, Clone, Debug, PartialEq, Eq, Default)]
+            #[repr(transparent)]
+            pub struct $name(pub $(&inner));
+
+            impl bytecheck::CheckBytes<piton::types::TailContext> for $name {
+                type Error = piton::PathCheckError;
+
+                unsafe fn check_bytes<'a>(value: *const Self, context: &mut piton::types::TailContext) -> Result<&'a Self, Self::Error> {
+                    $(check_field(&inner, quote!(core::ptr::addr_of!((*value).0)), quote!(piton::PathSegment::Index(0))))
+                    Ok(&*value)
+                }
+            }
+
+            impl From<$(&inner)> for $name {
+                fn from(value: $(&inner)) -> Self {
+                    Self(value)
+                }
+            }
+
+            impl From<$name> for $(&inner) {
+                fn from(value: $name) -> Self {
+                    value.0
+                }
+            }
+        };
+        tokens.to_file_string().into_diagnostic()
+    }
+}
+
+/// The rust type bitfield members are read and written as
+fn bitfield_member_ty(ty: &crate::BitfieldTy) -> String {
+    match ty {
+        crate::BitfieldTy::Bool => "bool".to_string(),
+        crate::BitfieldTy::Uint(ty) => native_uint(ty).to_string(),
+        crate::BitfieldTy::Enum { name, .. } => name.clone(),
+    }
+}
+
+fn native_uint(ty: &Ty) -> &'static str {
+    match ty.uint_width() {
+        Some(8) => "u8",
+        Some(16) => "u16",
+        Some(32) => "u32",
+        _ => "u64",
+    }
+}
+
+/// Generates the integer newtype that a run of bitfield members is packed into,
+/// along with accessors for each member and validation of their values
+fn generate_bitfield_unit(unit: &crate::BitfieldUnit) -> miette::Result<rust::Tokens> {
+    let name = &unit.ty_name;
+    let native = native_uint(&unit.ty);
+    let storage_width = |ty: &Ty| ty.uint_width().unwrap_or(64);
+    let read = |value: &str| {
+        if unit.ty == Ty::U8 {
+            format!("{}.0", value)
+        } else {
+            format!("{}::from({}.0)", native, value)
+        }
+    };
+    let raw_value = |member: &crate::BitfieldMember, value: &str| {
+        let mask = u64::MAX >> (64 - member.width);
+        if member.width == storage_width(&unit.ty) {
+            read(value)
+        } else if member.offset == 0 {
+            format!("{} & {:#x}", read(value), mask)
+        } else {
+            format!("({} >> {}) & {:#x}", read(value), member.offset, mask)
+        }
+    };
+
+    let mut checks = vec![];
+    let methods: Vec<rust::Tokens> = unit
+        .members
+        .iter()
+        .map(|member| {
+            let mask = u64::MAX >> (64 - member.width);
+            let raw = raw_value(member, "self");
+            let (get, set) = match &member.ty {
+                crate::BitfieldTy::Bool => {
+                    if member.width > 1 {
+                        checks.push((member.name.clone(), raw_value(member, "self"), 1));
+                    }
+                    (format!("{} != 0", raw), format!("{}::from(value)", native))
+                }
+                crate::BitfieldTy::Uint(ty) => {
+                    let get = if native_uint(ty) == native {
+                        raw
+                    } else {
+                        format!("({}) as {}", raw, native_uint(ty))
+                    };
+                    let set = if native_uint(ty) == native {
+                        "value".to_string()
+                    } else if storage_width(ty) < storage_width(&unit.ty) {
+                        format!("{}::from(value)", native)
+                    } else {
+                        format!("value as {}", native)
+                    };
+                    (get, set)
+                }
+                crate::BitfieldTy::Enum { name, variants } => {
+                    if member.width >= 64 || variants.len() < 1 << member.width {
+                        checks.push((
+                            member.name.clone(),
+                            raw_value(member, "self"),
+                            variants.len() - 1,
+                        ));
+                    }
+                    let arms = variants
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| format!("{} => {}::{},", i, name, v.to_case(Case::Pascal)))
+                        .collect::<String>();
+                    (
+                        format!("match {} {{ {} _ => unreachable!() }}", raw, arms),
+                        format!("value as {}", native),
+                    )
+                }
+            };
+            let bits = read("self");
+            let write = if member.width == storage_width(&unit.ty) {
+                set
+            } else if member.offset == 0 {
+                format!("({} & !{:#x}) | ({} & {:#x})", bits, mask, set, mask)
+            } else {
+                format!(
+                    "({bits} & !({mask:#x} << {offset})) | (({set} & {mask:#x}) << {offset})",
+                    bits = bits,
+                    mask = mask,
+                    set = set,
+                    offset = member.offset
+                )
+            };
+            let write = if unit.ty == Ty::U8 {
+                format!("self.0 = {};", write)
+            } else if member.width == storage_width(&unit.ty) {
+                format!("self.0 = {}.into();", write)
+            } else {
+                format!("self.0 = ({}).into();", write)
+            };
+            let member_ty = bitfield_member_ty(&member.ty);
+            quote! {
+                pub fn $(&member.name)(&self) -> $(&member_ty) {
+                    $(get)
+                }
+
+                pub fn set_$(&member.name)(&mut self, value: $(&member_ty)) {
+                    $(write)
+                }
+            }
+        })
+        .collect();
+
+    let (_, _, bits, from_bits) = TypeGenerator::flags_storage(&unit.ty)?;
+    let (validate, check): (rust::Tokens, rust::Tokens) = if checks.is_empty() {
+        let validate = quote! {
+            /// Makes a unit out of the bits its members are packed into
+            pub fn from_bits(bits: $native) -> Option<Self> {
+                Some($(&from_bits))
+            }
+        };
+        let check = quote! {
+            impl<C: ?Sized> bytecheck::CheckBytes<C> for $name {
+                type Error = core::convert::Infallible;
+
+                unsafe fn check_bytes<'a>(value: *const Self, _: &mut C) -> Result<&'a Self, Self::Error> {
+                    Ok(&*value)
+                }
+            }
+        };
+        (validate, check)
+    } else {
+        let validate = quote! {
+            /// Makes a unit out of the bits its members are packed into, returning `None` if
+            /// a member's bits don't hold one of its values
+            pub fn from_bits(bits: $native) -> Option<Self> {
+                let value = $(&from_bits);
+                value.validate().ok().map(|()| value)
+            }
+
+            fn validate(&self) -> Result<(), piton::types::InvalidBitfieldError> {
+                $(for (name, raw, max) in &checks {
+                    if $(raw) > $(*max) {
+                        return Err(piton::types::InvalidBitfieldError { field_name: $(quoted(name)) });
+                    }
+                })
+                Ok(())
+            }
+        };
+        let check = quote! {
+            impl<C: ?Sized> bytecheck::CheckBytes<C> for $name {
+                type Error = piton::types::InvalidBitfieldError;
+
+                unsafe fn check_bytes<'a>(value: *const Self, _: &mut C) -> Result<&'a Self, Self::Error> {
+                    let value = &*value;
+                    value.validate()?;
+                    Ok(value)
+                }
+            }
+        };
+        (validate, check)
+    };
+
+    Ok(quote! {
+        #[derive(piton::Yule, Clone, Copy, PartialEq, Eq, Hash, Default)]
+        #[repr(transparent)]
+        pub struct $name($(ty_to_rust(&unit.ty)));
+
+        impl $name {
+            $(validate)
+
+            /// Returns the bits the members are packed into
+            pub fn bits(&self) -> $native {
+                $(&bits)
+            }
+
+            $(for m in methods => $(m))
+        }
+
+        impl core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.debug_struct($(quoted(name)))
+                    $(for m in &unit.members => .field($(quoted(&m.name)), &self.$(&m.name)()))
+                    .finish()
+            }
+        }
+
+        $(check)
+    })
+}
+
+impl TypeGenerator {
+    fn flags_storage(ty: &Ty) -> miette::Result<(&'static str, String, String, String)> {
+        Ok(match ty {
+            Ty::U8 => (
+                "u8",
+                ty_to_rust(ty),
+                "self.0".to_string(),
+                "Self(bits)".to_string(),
+            ),
+            Ty::U16 | Ty::U32 | Ty::U64 | Ty::U16Be | Ty::U32Be | Ty::U64Be => {
+                let native = native_uint(ty);
+                let storage = ty_to_rust(ty);
+                (
+                    native,
+                    storage.clone(),
+                    format!("{}::from(self.0)", native),
+                    format!("Self({}::new(bits))", storage),
+                )
+            }
+            _ => return Err(miette!("flags must be backed by an unsigned integer")),
+        })
+    }
 }
 
 impl GenericArg {
@@ -105,18 +642,74 @@ impl GenericTy {
     }
 }
 
+/// Returns the `ASSERT_LAYOUT` of a generated enum. The derive only handles structs, so this evaluates
+/// the checks of the enum's payloads, which wouldn't run otherwise if they're generic.
+fn enum_layout_assert<'a>(payloads: impl IntoIterator<Item = &'a Ty>) -> rust::Tokens {
+    let payloads: Vec<String> = payloads.into_iter().map(yule_element).collect();
+    if payloads.is_empty() {
+        return quote!();
+    }
+    quote! {
+        const ASSERT_LAYOUT: () = {
+            $(for ty in payloads => let () = <$ty as piton::Yule>::ASSERT_LAYOUT;)
+        };
+    }
+}
+
+/// Returns the Rust type of `ty`, or of its elements if it is an array, which is what implements `Yule`
+fn yule_element(ty: &Ty) -> String {
+    match ty {
+        Ty::Array { ty, .. } => yule_element(ty),
+        ty => ty_to_rust(ty),
+    }
+}
+
+/// Checks the field of type `ty` at `ptr` in a generated `check_bytes`, putting `segment` in front of the path of its error
+fn check_field(ty: &str, ptr: rust::Tokens, segment: rust::Tokens) -> rust::Tokens {
+    quote! {
+        <$ty as bytecheck::CheckBytes<piton::types::TailContext>>::check_bytes($ptr, context)
+            .map_err(|e| piton::PathCheckError::within($segment, &e))?;
+    }
+}
+
 fn ty_to_rust(ty: &Ty) -> String {
     match ty {
+        Ty::U128 => "piton::types::u128le".to_string(),
+        Ty::I128 => "piton::types::i128le".to_string(),
+        Ty::U128Be => "piton::types::u128be".to_string(),
+        Ty::I128Be => "piton::types::i128be".to_string(),
+        Ty::Char => "piton::types::charle".to_string(),
+        Ty::String(len) => format!("piton::types::String<{}>", len),
+        Ty::CharBe => "piton::types::charbe".to_string(),
+        Ty::NonZero(ty) => format!("piton::types::NonZero<{}>", ty_to_rust(ty)),
         Ty::U64 => "piton::types::u64le".to_string(),
         Ty::U32 => "piton::types::u32le".to_string(),
         Ty::U16 => "piton::types::u16le".to_string(),
         Ty::U8 => "u8".to_string(),
         Ty::I64 => "piton::types::i64le".to_string(),
-        Ty::I32 => "i32le".to_string(),
-        Ty::I16 => "i16le".to_string(),
-        Ty::I8 => "piton::types::i8".to_string(),
+        Ty::I32 => "piton::types::i32le".to_string(),
+        Ty::I16 => "piton::types::i16le".to_string(),
+        Ty::I8 => "i8".to_string(),
+        Ty::U64Be => "piton::types::u64be".to_string(),
+        Ty::U32Be => "piton::types::u32be".to_string(),
+        Ty::U16Be => "piton::types::u16be".to_string(),
+        Ty::I64Be => "piton::types::i64be".to_string(),
+        Ty::I32Be => "piton::types::i32be".to_string(),
+        Ty::I16Be => "piton::types::i16be".to_string(),
         Ty::Bool => "bool".to_string(),
         Ty::Array { ty, len } => format!("[{}; {}]", ty_to_rust(ty), len),
+        Ty::Vec { ty, len } => format!("piton::types::Vec<{}, {}>", ty_to_rust(ty), len),
+        Ty::Option(ty) => format!("piton::types::Option<{}>", ty_to_rust(ty)),
+        Ty::Str => "piton::types::Str".to_string(),
+        Ty::Bytes => "piton::types::Bytes".to_string(),
+        Ty::Slice(ty) => format!("piton::types::Slice<{}>", ty_to_rust(ty)),
+        Ty::Map { key, value, len } => format!(
+            "piton::types::Map<{}, {}, {}>",
+            ty_to_rust(key),
+            ty_to_rust(value),
+            len
+        ),
+        Ty::Set { ty, len } => format!("piton::types::Set<{}, {}>", ty_to_rust(ty), len),
         Ty::Unresolved { name, generic_args } => {
             let args = if generic_args.is_empty() {
                 "".to_string()
@@ -132,68 +725,356 @@ fn ty_to_rust(ty: &Ty) -> String {
             };
             format!("{}{}", name, args)
         }
-        Ty::F32 => "f32".to_string(),
-        Ty::F64 => "f64".to_string(),
+        Ty::F32 => "piton::types::f32le".to_string(),
+        Ty::F64 => "piton::types::f64le".to_string(),
+        Ty::F32Be => "piton::types::f32be".to_string(),
+        Ty::F64Be => "piton::types::f64be".to_string(),
         Ty::Extern(e) => e.clone(),
     }
 }
 
-pub struct ServiceGenerator;
+/// Handlers are passed a `&str` for string arguments, rather than the fixed-capacity string itself
+fn handler_arg_to_rust(ty: &Ty) -> String {
+    match ty {
+        Ty::String(_) => "str".to_string(),
+        ty => ty_to_rust(ty),
+    }
+}
+
+#[derive(Default)]
+pub struct ServiceGenerator {
+    /// Generate an async server for `piton::AsyncServiceRx` transports, with async handlers
+    pub async_: bool,
+    /// Generate a server for `piton::DeferRx` transports, whose handlers are given the reply to send when they're ready
+    pub deferred: bool,
+}
 
 impl crate::ServiceGenerator for ServiceGenerator {
     fn generate_service(&self, service: &crate::Service) -> miette::Result<String> {
+        let flavor = Flavor(self.async_);
+        let service_rx = flavor.transport("ServiceRx");
         let pascal_name = service.ty_def.name.to_case(Case::Pascal);
         let mut hasher = DefaultHasher::default();
         hasher.write(service.ty_def.name.as_bytes());
-        let trait_methods: Vec<rust::Tokens> = service.methods.iter().map(|method| {
-            quote! {
-                fn $(method.name.to_case(Case::Snake))(&mut self, msg: &$(ty_to_rust(&method.arg_ty)), resp: &mut $(ty_to_rust(&method.return_ty))) -> Result<(), piton::Error>;
+        let service_rx = if self.deferred {
+            flavor.transport("DeferRx")
+        } else {
+            service_rx
+        };
+        let trait_methods: Vec<rust::Tokens> = service.methods.iter().filter(|m| m.base.is_none()).map(|method| {
+            if self.deferred {
+                quote! {
+                    $(flavor.async_()) fn $(method.name.to_case(Case::Snake))(&mut self, ctx: &piton::Context$(context_args(service)), msg: &$(handler_arg_to_rust(&method.arg_ty)), req_tail: &[u8], reply: $(method.name.to_case(Case::Pascal))Reply<T, $(for t in &service.ty_def.generic_tys => $(t.to_rust()))>) -> Result<(), piton::Error>;
+                }
+            } else {
+                quote! {
+                    $(flavor.async_()) fn $(method.name.to_case(Case::Snake))(&mut self, ctx: &piton::Context$(context_args(service)), msg: &$(handler_arg_to_rust(&method.arg_ty)), resp: &mut $(ty_to_rust(&method.return_ty)), tails: piton::Tails<'_>) -> Result<(), piton::Error>;
+                }
             }
         }).collect();
 
-        let match_arms: Vec<rust::Tokens> = service
-            .methods
+        let super_traits: Vec<String> = service
+            .ancestors
+            .iter()
+            .map(|base| {
+                format!(
+                    "{}Service<T, {}>",
+                    base.name.to_case(Case::Pascal),
+                    generic_args_to_rust(&base.generic_args)
+                )
+            })
+            .collect();
+        let super_traits = if super_traits.is_empty() {
+            String::default()
+        } else {
+            format!(": {}", super_traits.join(" + "))
+        };
+
+        let match_arms: Vec<rust::Tokens> = service
+            .methods
+            .iter()
+            .map(|method| {
+                if self.deferred {
+                    return quote! {
+                        $(&pascal_name)ReqRef::$(method.name.to_case(Case::Pascal))(arg) => {
+                            // The buffer is zeroed, and Yule types have nothing to drop
+                            unsafe { core::ptr::write(resp.as_mut(), $(&pascal_name)Ret::$(method.name.to_case(Case::Pascal))(Default::default())) };
+                            let reply = $(method.name.to_case(Case::Pascal))Reply { reply: Some((resp, responder)), _phantom: core::marker::PhantomData };
+                            // The handler holds the reply now, and it is failed if the handler drops it without sending it
+                            let _ = service.$(method.name.to_case(Case::Snake))(&ctx, arg, req, reply)$(flavor.await_());
+                            Ok(())
+                        }
+                    };
+                }
+                quote! {
+                    $(&pascal_name)ReqRef::$(method.name.to_case(Case::Pascal))(arg) => {
+                        let req = recv.req.tail();
+                        let (resp, tail) = recv.resp.split_tail();
+                        // The buffer is zeroed, and Yule types have nothing to drop
+                        unsafe { core::ptr::write(resp, $(&pascal_name)Ret::$(method.name.to_case(Case::Pascal))(Default::default())) };
+                        let $(&pascal_name)Ret::$(method.name.to_case(Case::Pascal))(resp) = resp else {
+                            unreachable!()
+                        };
+                        match service.$(method.name.to_case(Case::Snake))(&ctx, arg, resp, piton::Tails { req, resp: tail })$(flavor.await_()) {
+                            Ok(()) => piton::Status::Ok,
+                            Err(_) => piton::Status::HandlerError,
+                        }
+                    }
+                }
+            })
+            .collect();
+
+        let generic_args: rust::Tokens = if service.ty_def.generic_tys.is_empty() {
+            quote! {}
+        } else {
+            quote! {
+                $(for t in &service.ty_def.generic_tys => $(t.to_rust()))
+            }
+        };
+
+        let phantom_tys = phantom_tys(&service.ty_def.generic_tys);
+        let phantom_new = phantom_new(&service.ty_def.generic_tys);
+
+        let generic_tys = quote! { $(for t in &service.ty_def.generic_tys => $(t.to_rust()): piton::Yule + 'static,) };
+
+        let generic_enum_args: rust::Tokens = if service.ty_def.generic_tys.is_empty() {
+            quote! {}
+        } else {
+            quote! {
+                <$(for t in &service.ty_def.generic_tys => $(t.to_rust()))>
+            }
+        };
+
+        let generic_phantom_args: rust::Tokens = if service.ty_def.generic_tys.is_empty() {
+            quote! {()}
+        } else {
+            quote! {
+                ($(for t in &service.ty_def.generic_tys => $(t.to_rust())))
+            }
+        };
+
+        let reply_structs: Vec<rust::Tokens> = service
+            .methods
+            .iter()
+            .filter(|m| self.deferred && m.base.is_none())
+            .map(|method| {
+                let method_pascal = method.name.to_case(Case::Pascal);
+                let method_snake = method.name.to_case(Case::Snake);
+                let return_ty = ty_to_rust(&method.return_ty);
+                let ret_variants = quote! { $(&pascal_name)RetVariants<$(&generic_args)> };
+                quote! {
+                    #[doc = $(quoted(format!(" The reply to a `{}` request, which the handler can hold on to and send once it is ready", method_snake)))]
+                    pub struct $(&method_pascal)Reply<T: $(&service_rx), $(&generic_tys)> {
+                        /// The return value and where to send it, until it is sent
+                        #[allow(clippy::type_complexity)]
+                        reply: Option<(T::BufW<'static>, T::Responder<'static>)>,
+                        #[allow(unused_parens)]
+                        _phantom: core::marker::PhantomData<$(&generic_phantom_args)>
+                    }
+
+                    impl<T: $(&service_rx), $(&generic_tys)> $(&method_pascal)Reply<T, $(&generic_args)> where T::Ret: $(&ret_variants) {
+                        /// Sends the reply
+                        pub $(flavor.async_()) fn send(mut self) -> Result<(), piton::Error> {
+                            use $(flavor.transport("Responder"));
+                            let (resp, responder) = self.reply.take().expect("replies are only taken when they're sent");
+                            responder.send(resp)$(flavor.await_())
+                        }
+
+                        /// Replies with `status` in place of a return value, for a request the handler couldn't handle
+                        pub $(flavor.async_()) fn fail(mut self, status: piton::Status) -> Result<(), piton::Error> {
+                            use piton::{BufW, Reply};
+                            self.resp_mut().as_mut().set_status(status);
+                            self.send()$(flavor.await_())
+                        }
+
+                        /// Calls `f` with the return value and a writer for the trailing region its variable-length fields point into
+                        pub fn with_tail<R>(&mut self, f: impl FnOnce(&mut $(&return_ty), piton::types::TailWriter<'_>) -> R) -> R {
+                            use piton::BufW;
+                            self.resp_mut().with_tail(|msg, tail| match <T::Ret as $(&ret_variants)>::as_$(&method_snake)_mut(msg) {
+                                Some(v) => f(v, tail),
+                                None => unreachable!()
+                            })
+                        }
+
+                        fn resp(&self) -> &T::BufW<'static> {
+                            &self.reply.as_ref().expect("replies are only taken when they're sent").0
+                        }
+
+                        fn resp_mut(&mut self) -> &mut T::BufW<'static> {
+                            &mut self.reply.as_mut().expect("replies are only taken when they're sent").0
+                        }
+                    }
+
+                    impl<T: $(&service_rx), $(&generic_tys)> Drop for $(&method_pascal)Reply<T, $(&generic_args)> {
+                        /// Fails the reply with [`piton::Status::HandlerError`] if it wasn't sent, so that the client isn't left waiting
+                        fn drop(&mut self) {
+                            use piton::{BufW, Reply};
+                            if let Some((mut resp, responder)) = self.reply.take() {
+                                resp.as_mut().set_status(piton::Status::HandlerError);
+                                T::send_dropped(resp, responder);
+                            }
+                        }
+                    }
+
+                    impl<T: $(&service_rx), $(&generic_tys)> core::ops::Deref for $(&method_pascal)Reply<T, $(&generic_args)> where T::Ret: $(&ret_variants) {
+                        type Target = $(&return_ty);
+
+                        fn deref(&self) -> &Self::Target {
+                            match <T::Ret as $(&ret_variants)>::as_$(&method_snake)(core::ops::Deref::deref(self.resp())) {
+                                Some(v) => v,
+                                None => unreachable!()
+                            }
+                        }
+                    }
+
+                    impl<T: $(&service_rx), $(&generic_tys)> core::ops::DerefMut for $(&method_pascal)Reply<T, $(&generic_args)> where T::Ret: $(&ret_variants) {
+                        fn deref_mut(&mut self) -> &mut Self::Target {
+                            match <T::Ret as $(&ret_variants)>::as_$(&method_snake)_mut(core::ops::DerefMut::deref_mut(self.resp_mut())) {
+                                Some(v) => v,
+                                None => unreachable!()
+                            }
+                        }
+                    }
+                }
+            })
+            .collect();
+
+        let bus_tx = flavor.transport("BusTx");
+        let event_tx = flavor.transport("EventTx");
+        let ret_enum = quote! { $(&pascal_name)Ret$(&generic_enum_args) };
+        let publish_structs: Vec<rust::Tokens> = service
+            .events
+            .iter()
+            .map(|event| {
+                let event_pascal = event.name.to_case(Case::Pascal);
+                let ty = ty_to_rust(&event.ty);
+                quote! {
+                    pub struct $(&event_pascal)PublishRef<'a, P: $(&bus_tx) + 'a, $(&generic_tys)> {
+                        transport: &'a mut P,
+                        msg: <P as $(&bus_tx)>::BufW<'a>,
+                        #[allow(unused_parens)]
+                        _phantom: core::marker::PhantomData<$(&generic_phantom_args)>
+                    }
+
+                    impl<'a, P: $(&bus_tx)<Msg = $(&ret_enum)> + 'a, $(&generic_tys)> $(&event_pascal)PublishRef<'a, P, $(&generic_args)> {
+                        /// Sends the event to every client of the server
+                        pub $(flavor.async_()) fn send(self) -> Result<(), piton::Error> {
+                            self.transport.send(self.msg)$(flavor.await_())
+                        }
+
+                        /// Calls `f` with the event and a writer for the trailing region its variable-length fields point into
+                        pub fn with_tail<R>(&mut self, f: impl FnOnce(&mut $(&ty), piton::types::TailWriter<'_>) -> R) -> R {
+                            use piton::BufW;
+                            self.msg.with_tail(|msg, tail| match msg {
+                                $(&pascal_name)Ret::$(&event_pascal)(v) => f(v, tail),
+                                _ => unreachable!()
+                            })
+                        }
+                    }
+
+                    impl<'a, P: $(&bus_tx)<Msg = $(&ret_enum)> + 'a, $(&generic_tys)> core::ops::Deref for $(&event_pascal)PublishRef<'a, P, $(&generic_args)> {
+                        type Target = $(&ty);
+
+                        fn deref(&self) -> &Self::Target {
+                            match core::ops::Deref::deref(&self.msg) {
+                                $(&pascal_name)Ret::$(&event_pascal)(v) => v,
+                                _ => unreachable!()
+                            }
+                        }
+                    }
+
+                    impl<'a, P: $(&bus_tx)<Msg = $(&ret_enum)> + 'a, $(&generic_tys)> core::ops::DerefMut for $(&event_pascal)PublishRef<'a, P, $(&generic_args)> {
+                        fn deref_mut(&mut self) -> &mut Self::Target {
+                            match core::ops::DerefMut::deref_mut(&mut self.msg) {
+                                $(&pascal_name)Ret::$(&event_pascal)(v) => v,
+                                _ => unreachable!()
+                            }
+                        }
+                    }
+                }
+            })
+            .collect();
+
+        let publish_methods: Vec<rust::Tokens> = service
+            .events
             .iter()
-            .map(|method| {
+            .map(|event| {
+                let event_pascal = event.name.to_case(Case::Pascal);
                 quote! {
-                    $(&pascal_name)Req::$(method.name.to_case(Case::Pascal))(arg) => {
-                        {
-                            *recv.resp = $(&pascal_name)Ret::$(method.name.to_case(Case::Pascal))(Default::default());
-                            #[allow(irrefutable_let_patterns)]
-                            let $(&pascal_name)Ret::$(method.name.to_case(Case::Pascal))(resp) = &mut *recv.resp else {
-                                unreachable!()
-                            };
-                            self.service.$(method.name.to_case(Case::Snake))(arg, resp)?;
-                        }
-                        recv.responder.send(recv.resp)?;
+                    pub $(flavor.async_()) fn $(event.name.to_case(Case::Snake))_ref(&mut self) -> Result<$(&event_pascal)PublishRef<'_, P, $(&generic_args)>, piton::Error> {
+                        use piton::BufW;
+                        let mut msg = self.transport.alloc()$(flavor.await_())?;
+                        // The buffer is zeroed, and Yule types have nothing to drop
+                        unsafe { core::ptr::write(msg.as_mut(), $(&pascal_name)Ret::$(&event_pascal)(Default::default())) };
+                        Ok($(&event_pascal)PublishRef {
+                            msg,
+                            transport: &mut self.transport,
+                            _phantom: core::marker::PhantomData,
+                        })
                     }
                 }
             })
             .collect();
 
-        let generic_args: rust::Tokens = if service.ty_def.generic_tys.is_empty() {
+        let publisher: rust::Tokens = if service.events.is_empty() {
             quote! {}
         } else {
             quote! {
-                $(for t in &service.ty_def.generic_tys => $(t.to_rust()))
-            }
-        };
+                $(for s in publish_structs => $(s))
 
-        let phantom_tys = phantom_tys(&service.ty_def.generic_tys);
-        let phantom_new = phantom_new(&service.ty_def.generic_tys);
+                #[doc = $(quoted(format!(" Publishes `{}` events to the clients of a server", service.ty_def.name)))]
+                pub struct $(&pascal_name)Publisher<P, $(&generic_tys)> {
+                    pub transport: P,
+                    $(phantom_tys.clone())
+                }
 
-        let generic_tys = quote! { $(for t in &service.ty_def.generic_tys => $(t.to_rust()): piton::Yule + 'static,) };
+                impl<P: $(&bus_tx)<Msg = $(&ret_enum)>, $(&generic_tys)> $(&pascal_name)Publisher<P, $(&generic_args)> {
+                    pub fn new(transport: P) -> Self {
+                        Self {
+                            transport,
+                            $(phantom_new.clone())
+                        }
+                    }
 
-        let generic_enum_args: rust::Tokens = if service.ty_def.generic_tys.is_empty() {
-            quote! {}
+                    $(for m in publish_methods => $(m))
+                }
+            }
+        };
+
+        let handle_body: rust::Tokens = if self.deferred {
+            quote! {
+                let (mut resp, responder) = T::defer(recv.resp, recv.responder);
+                let req = recv.req.tail();
+                #[allow(unreachable_patterns)]
+                match recv.req.view() {
+                    $(for arm in match_arms => $(arm))
+                    _ => {
+                        resp.as_mut().set_status(piton::Status::UnknownMethod);
+                        responder.send(resp)$(flavor.await_())
+                    }
+                }
+            }
         } else {
             quote! {
-                <$(for t in &service.ty_def.generic_tys => $(t.to_rust()))>
+                #[allow(unreachable_patterns)]
+                let status = match recv.req.view() {
+                    $(for arm in match_arms => $(arm))
+                    _ => piton::Status::UnknownMethod,
+                };
+                if status != piton::Status::Ok {
+                    recv.resp.as_mut().set_status(status);
+                }
+                recv.responder.send(recv.resp)$(flavor.await_())
             }
         };
 
         let tokens: rust::Tokens = quote! {
-            pub trait $(&pascal_name)Service<T: piton::ServiceRx, $(&generic_tys)> {
+            $(for reply in reply_structs => $(reply))
+
+            $(publisher)
+
+            $(flavor.allow_async_fn_in_trait())
+            pub trait $(&pascal_name)Service<T: $(&service_rx), $(&generic_tys)>$(super_traits) {
                 $(for method in trait_methods => $(method))
             }
 
@@ -204,7 +1085,7 @@ impl crate::ServiceGenerator for ServiceGenerator {
             }
 
 
-            impl<T: piton::ServiceRx<Arg = $(&pascal_name)Req$(&generic_enum_args), Ret = $(&pascal_name)Ret$(&generic_enum_args)>, S: $(&pascal_name)Service<T, $(&generic_args)>, $(&generic_tys)> $(&pascal_name)Server<T, S, $(&generic_args)> {
+            impl<T: $(&service_rx)<Arg = $(&pascal_name)Req$(&generic_enum_args), Ret = $(&pascal_name)Ret$(&generic_enum_args)>, S: $(&pascal_name)Service<T, $(&generic_args)>, $(&generic_tys)> $(&pascal_name)Server<T, S, $(&generic_args)> {
                 pub fn new(transport: T, service: S) -> Self {
                     Self {
                         transport,
@@ -213,28 +1094,95 @@ impl crate::ServiceGenerator for ServiceGenerator {
                     }
                 }
 
-                pub fn run(mut self) -> Result<(), piton::Error> {
-                    use piton::Responder;
-                    while let Some(mut recv) = self.transport.recv()? {
-                        use piton::BufR;
-                        #[allow(clippy::single_match, unreachable_patterns)]
-                        match recv.req.as_ref() {
-                            $(for arm in match_arms => $(arm))
-                            _ => {}
-                        }
+                $(if !service.events.is_empty() {
+                    /// Returns a publisher that sends events to the server's clients. It can be made before the server runs
+                    /// and moved elsewhere.
+                    pub fn publisher(&self) -> $(&pascal_name)Publisher<<T as $(&event_tx)>::Publisher, $(&generic_args)> where T: $(&event_tx) {
+                        $(&pascal_name)Publisher::new(<T as $(&event_tx)>::publisher(&self.transport))
+                    }
+                })
+
+                /// Handles requests until the transport is closed
+                pub $(flavor.async_()) fn run(mut self) -> Result<(), piton::Error> {
+                    while let Some(recv) = self.transport.recv()$(flavor.await_())? {
+                        Self::handle(&mut self.service, recv)$(flavor.await_())?;
                     }
                     Ok(())
                 }
+
+                $(if self.async_ {
+                    /// Handles requests until the transport is closed or `shutdown` is called
+                    pub async fn run_until(mut self, shutdown: &piton::Shutdown) -> Result<(), piton::Error> {
+                        while let Some(recv) = shutdown.until(self.transport.recv()).await {
+                            let Some(recv) = recv? else {
+                                break;
+                            };
+                            Self::handle(&mut self.service, recv).await?;
+                        }
+                        Ok(())
+                    }
+                } else {
+                    /// Handles requests until the transport is closed or `shutdown` is called
+                    pub fn run_until(mut self, shutdown: &piton::Shutdown) -> Result<(), piton::Error> {
+                        while let Some(recv) = self.transport.recv_until(shutdown)? {
+                            Self::handle(&mut self.service, recv)?;
+                        }
+                        Ok(())
+                    }
+                })
+
+                $(if !self.async_ {
+                    /// Handles at most one request without blocking, returning whether there was one.
+                    /// Lets a superloop interleave the server with other work.
+                    pub fn poll(&mut self) -> Result<bool, piton::Error> {
+                        match self.transport.try_recv()? {
+                            Some(recv) => {
+                                Self::handle(&mut self.service, recv)?;
+                                Ok(true)
+                            }
+                            None => Ok(false),
+                        }
+                    }
+                })
+
+                $(if self.deferred {
+                    /// Hands the reply to the handler, or replies with [`piton::Status::UnknownMethod`] if there isn't one.
+                    /// A handler's error doesn't stop the server, and a reply the handler drops without sending
+                    /// it is failed with [`piton::Status::HandlerError`].
+                } else {
+                    /// Replies with a [`piton::Status`] in place of a return value if the handler fails, so that
+                    /// only errors from the transport stop the server
+                })
+                #[allow(clippy::type_complexity, unused_mut)]
+                $(flavor.async_()) fn handle(service: &mut S, mut recv: piton::Recv<T::BufW<'_>, T::BufR<'_>, T::Responder<'_>>) -> Result<(), piton::Error> {
+                    use $(flavor.transport("Responder"));
+                    use piton::{BufR, BufW, Reply};
+                    $(if service.header.is_some() {
+                        let header = <$(&pascal_name)Req$(&generic_enum_args) as piton::Headed>::frame_header(recv.req.frame())
+                            .expect("frames are validated when they're received")
+                            .clone();
+                        let ctx = recv.ctx.with_header(header);
+                    } else {
+                        let ctx = recv.ctx;
+                    })
+                    $(handle_body)
+                }
             }
         };
         tokens.to_file_string().into_diagnostic()
     }
 }
 
-pub struct ClientGenerator;
+#[derive(Default)]
+pub struct ClientGenerator {
+    /// Generate an async client for `piton::AsyncServiceTx` transports
+    pub async_: bool,
+}
 
 impl crate::ServiceGenerator for ClientGenerator {
     fn generate_service(&self, service: &crate::Service) -> miette::Result<String> {
+        let flavor = Flavor(self.async_);
+        let service_tx = flavor.transport("ServiceTx");
         let mut hasher = DefaultHasher::default();
         hasher.write(service.ty_def.name.as_bytes());
         let pascal_name = service.ty_def.name.to_case(Case::Pascal);
@@ -266,77 +1214,188 @@ impl crate::ServiceGenerator for ClientGenerator {
 
         let methods: Vec<rust::Tokens> = service.methods.iter().map(|method| {
             let method_pascal = method.name.to_case(Case::Pascal);
+            let method_snake = method.name.to_case(Case::Snake);
+            let ref_args = match &method.base {
+                Some(base) => generic_args_to_rust(&base.generic_args),
+                None => generic_args.to_string().into_diagnostic()?,
+            };
 
-            quote! {
-                pub fn $(method.name.to_case(Case::Snake))_ref(&mut self) -> Result<$(&method_pascal)CallRef<'_, T, $(&generic_args)>, piton::Error> {
+            Ok(quote! {
+                pub $(flavor.async_()) fn $(&method_snake)_ref(&mut self) -> Result<$(&method_pascal)CallRef<'_, T, $(ref_args)>, piton::Error> {
+                    let mut msg = self.transport.alloc()$(flavor.await_())?;
+                    // The buffer is zeroed, and Yule types have nothing to drop
+                    unsafe { core::ptr::write(&mut *msg, $(&pascal_name)Req::$(&method_pascal)($(header_hole(service, "Default::default()"))Default::default())) };
                     Ok($(method_pascal)CallRef {
-                        msg: self.transport.alloc()?,
+                        msg,
                         transport: &mut self.transport,
                         _phantom: core::marker::PhantomData,
                     })
                 }
+            })
+        }).collect::<miette::Result<_>>()?;
+
+        let calls_methods: Vec<rust::Tokens> = service.methods.iter().filter(|m| m.base.is_none()).map(|method| {
+            quote! {
+                $(flavor.async_()) fn $(method.name.to_case(Case::Snake))_ref<'a>(&'a mut self) -> Result<$(method.name.to_case(Case::Pascal))CallRef<'a, Self::Transport, $(&generic_args)>, piton::Error> where Self::Transport: 'a;
             }
         }).collect();
 
+        let calls_impls: Vec<rust::Tokens> = service
+            .ancestors
+            .iter()
+            .map(|base| (base.name.as_str(), generic_args_to_rust(&base.generic_args)))
+            .chain([(
+                service.ty_def.name.as_str(),
+                generic_args.to_string().into_diagnostic()?,
+            )])
+            .map(|(owner, owner_args)| {
+                let owner_methods: Vec<rust::Tokens> = service
+                    .methods
+                    .iter()
+                    .filter(|m| method_owner(service, m) == owner)
+                    .map(|method| {
+                        let method_snake = method.name.to_case(Case::Snake);
+                        quote! {
+                            $(flavor.async_()) fn $(&method_snake)_ref<'a>(&'a mut self) -> Result<$(method.name.to_case(Case::Pascal))CallRef<'a, T, $(&owner_args)>, piton::Error> where T: 'a {
+                                Self::$(&method_snake)_ref(self)$(flavor.await_())
+                            }
+                        }
+                    })
+                    .collect();
+                quote! {
+                    impl<T: $(&service_tx)<Arg = $(&pascal_name)Req$(&generic_enum_args), Ret = $(&pascal_name)Ret$(&generic_enum_args)>, $(&generic_tys)> $(owner.to_case(Case::Pascal))Calls<$(&owner_args)> for $(&pascal_name)Client<T, $(&generic_args)> {
+                        type Transport = T;
+
+                        $(for method in owner_methods => $(method))
+                    }
+                }
+            })
+            .collect();
+
         let method_structs: Vec<rust::Tokens> = service
             .methods
             .iter()
+            .filter(|m| m.base.is_none())
             .map(|method| {
                 let arg_ty = &method.arg_ty;
                 let return_ty = &method.return_ty;
                 let method_pascal = method.name.to_case(Case::Pascal);
-                let req_enum = quote! { $(&pascal_name)Req };
-                let ret_enum = quote! { $(&pascal_name)Ret };
+                let method_snake = method.name.to_case(Case::Snake);
+                let req_variants = quote! { $(&pascal_name)ReqVariants<$(&generic_args)> };
+                let ret_variants = quote! { $(&pascal_name)RetVariants<$(&generic_args)> };
 
                 quote! {
-                    pub struct $(&method_pascal)CallRef<'a, Serv: piton::ServiceTx + 'a, $(&generic_tys)> {
+                    pub struct $(&method_pascal)CallRef<'a, Serv: $(&service_tx) + 'a, $(&generic_tys)> {
                         transport: &'a mut Serv,
-                        msg: <Serv as piton::ServiceTx>::BufW<'a>,
+                        msg: <Serv as $(&service_tx)>::BufW<'a>,
                         #[allow(unused_parens)]
                         _phantom: core::marker::PhantomData<$(&generic_phantom_args)>
                     }
 
-                    impl<'a, S: piton::ServiceTx + 'a, $(&generic_tys)> $(&method_pascal)CallRef<'a, S, $(&generic_args)> {
-                        pub fn call(self) -> Result<$(&method_pascal)RetRef<'a, S, $(&generic_args)>, piton::Error> {
-                            let msg = self.transport.call(self.msg)?;
-                            Ok($(&method_pascal)RetRef { msg, _phantom: core::marker::PhantomData })
+                    impl<'a, S: $(&service_tx) + 'a, $(&generic_tys)> $(&method_pascal)CallRef<'a, S, $(&generic_args)> {
+                        /// Calls the method and waits for the reply. Returns [`piton::Error::Status`] if the server couldn't handle the request.
+                        pub $(flavor.async_()) fn call(self) -> Result<$(&method_pascal)RetRef<'a, S, $(&generic_args)>, piton::Error> {
+                            $(&method_pascal)RetRef::new(self.transport.call(self.msg)$(flavor.await_())?)
+                        }
+
+                        /// Calls the method and waits at most `timeout` for the reply. Returns [`piton::Error::Timeout`] if it didn't come in time.
+                        pub $(flavor.async_()) fn call_timeout(self, timeout: core::time::Duration) -> Result<$(&method_pascal)RetRef<'a, S, $(&generic_args)>, piton::Error> {
+                            $(&method_pascal)RetRef::new(self.transport.call_timeout(self.msg, timeout)$(flavor.await_())?)
+                        }
+
+                        /// Sends the call without waiting for the reply, so that more calls can be made on the transport before it arrives
+                        pub $(flavor.async_()) fn start(self) -> Result<$(&method_pascal)PendingCall<S, $(&generic_args)>, piton::Error> {
+                            Ok($(&method_pascal)PendingCall {
+                                call: self.transport.start_call(self.msg)$(flavor.await_())?,
+                                _phantom: core::marker::PhantomData,
+                            })
+                        }
+
+                        $(if service.header.is_some() {
+                            /// Returns the header sent with the call
+                            pub fn header(&self) -> &<S::Arg as piton::Headed>::Header where S::Arg: piton::Headed {
+                                piton::Headed::header(core::ops::Deref::deref(&self.msg))
+                            }
+
+                            pub fn header_mut(&mut self) -> &mut <S::Arg as piton::Headed>::Header where S::Arg: piton::Headed {
+                                piton::Headed::header_mut(core::ops::DerefMut::deref_mut(&mut self.msg))
+                            }
+                        })
+
+                        /// Calls `f` with the argument and a writer for the trailing region its variable-length fields point into
+                        pub fn with_tail<R>(&mut self, f: impl FnOnce(&mut $(ty_to_rust(arg_ty)), piton::types::TailWriter<'_>) -> R) -> R where S::Arg: $(&req_variants) {
+                            use piton::BufW;
+                            self.msg.with_tail(|msg, tail| match <S::Arg as $(&req_variants)>::as_$(&method_snake)_mut(msg) {
+                                Some(v) => f(v, tail),
+                                None => unreachable!()
+                            })
                         }
                     }
 
-                    impl<'a, S: piton::ServiceTx<Arg = $(&req_enum)$(&generic_enum_args)> + 'a, $(&generic_tys)> core::ops::Deref for $(&method_pascal)CallRef<'a, S, $(&generic_args)> {
+                    impl<'a, S: $(&service_tx) + 'a, $(&generic_tys)> core::ops::Deref for $(&method_pascal)CallRef<'a, S, $(&generic_args)> where S::Arg: $(&req_variants) {
                         type Target = $(ty_to_rust(arg_ty));
 
                         fn deref(&self) -> &Self::Target {
-                            #[allow(irrefutable_let_patterns)]
-                            if let $(&req_enum)::$(&method_pascal)(v) = self.msg.deref() {
-                                v
-                            }else { unreachable!() }
+                            match <S::Arg as $(&req_variants)>::as_$(&method_snake)(self.msg.deref()) {
+                                Some(v) => v,
+                                None => unreachable!()
+                            }
                         }
                     }
 
-                    impl<'a, S: piton::ServiceTx<Arg = $(&req_enum)$(&generic_enum_args)> + 'a, $(&generic_tys)> core::ops::DerefMut for $(&method_pascal)CallRef<'a, S, $(&generic_args)> {
+                    impl<'a, S: $(&service_tx) + 'a, $(&generic_tys)> core::ops::DerefMut for $(&method_pascal)CallRef<'a, S, $(&generic_args)> where S::Arg: $(&req_variants) {
                         fn deref_mut(&mut self) -> &mut Self::Target {
-                            #[allow(irrefutable_let_patterns)]
-                            if let $(req_enum)::$(&method_pascal)(v) = self.msg.deref_mut() {
-                                v
-                            }else { unreachable!() }
+                            match <S::Arg as $(&req_variants)>::as_$(&method_snake)_mut(self.msg.deref_mut()) {
+                                Some(v) => v,
+                                None => unreachable!()
+                            }
                         }
                     }
 
-                    pub struct $(&method_pascal)RetRef<'a, Serv: piton::ServiceTx + 'a, $(&generic_tys)> {
-                        msg: <Serv as piton::ServiceTx>::BufR<'a>,
+                    pub struct $(&method_pascal)RetRef<'a, Serv: $(&service_tx) + 'a, $(&generic_tys)> {
+                        msg: <Serv as $(&service_tx)>::BufR<'a>,
                         #[allow(unused_parens)]
                         _phantom: core::marker::PhantomData<$(&generic_phantom_args)>
                     }
 
-                    impl<'a, S: piton::ServiceTx<Ret = $(&ret_enum)$(&generic_enum_args)> + 'a, $(&generic_tys)> core::ops::Deref for $(&method_pascal)RetRef<'a, S, $(&generic_args)> {
+                    #[must_use = "the reply is kept until the call is finished"]
+                    pub struct $(&method_pascal)PendingCall<Serv: $(&service_tx), $(&generic_tys)> {
+                        call: piton::PendingCall,
+                        #[allow(unused_parens)]
+                        _phantom: core::marker::PhantomData<(fn() -> Serv, $(&generic_phantom_args))>
+                    }
+
+                    impl<S: $(&service_tx), $(&generic_tys)> $(&method_pascal)PendingCall<S, $(&generic_args)> {
+                        /// Waits for the reply to the call. `transport` must be the one the call was started on.
+                        pub $(flavor.async_()) fn finish<'a>(self, transport: &'a mut S) -> Result<$(&method_pascal)RetRef<'a, S, $(&generic_args)>, piton::Error> where S: 'a {
+                            $(&method_pascal)RetRef::new(transport.finish_call(self.call)$(flavor.await_())?)
+                        }
+                    }
+
+                    impl<'a, S: $(&service_tx) + 'a, $(&generic_tys)> $(&method_pascal)RetRef<'a, S, $(&generic_args)> {
+                        /// Returns [`piton::Error::Status`] if the server couldn't handle the request
+                        fn new(msg: <S as $(&service_tx)>::BufR<'a>) -> Result<Self, piton::Error> {
+                            use piton::BufR;
+                            <S::Ret as piton::Reply>::status(&msg.view()).into_result()?;
+                            Ok(Self { msg, _phantom: core::marker::PhantomData })
+                        }
+
+                        /// Returns the trailing region the return value's variable-length fields point into
+                        pub fn tail(&self) -> &[u8] {
+                            use piton::BufR;
+                            self.msg.tail()
+                        }
+                    }
+
+                    impl<'a, S: $(&service_tx) + 'a, $(&generic_tys)> core::ops::Deref for $(&method_pascal)RetRef<'a, S, $(&generic_args)> where S::Ret: $(&ret_variants) {
                         type Target = $(ty_to_rust(return_ty));
 
                         fn deref(&self) -> &Self::Target {
-                            #[allow(irrefutable_let_patterns)]
-                            if let $(&ret_enum)::$(&method_pascal)(v) = self.msg.deref() {
-                                v
-                            }else { unreachable!() }
+                            use piton::BufR;
+                            match <S::Ret as $(&ret_variants)>::ref_as_$(&method_snake)(self.msg.view()) {
+                                Some(v) => v,
+                                None => unreachable!()
+                            }
                         }
                     }
 
@@ -347,15 +1406,99 @@ impl crate::ServiceGenerator for ClientGenerator {
         let phantom_tys = phantom_tys(&service.ty_def.generic_tys);
         let phantom_new = phantom_new(&service.ty_def.generic_tys);
 
+        let event_rx = flavor.transport("EventRx");
+        let ret_enum = quote! { $(&pascal_name)Ret$(&generic_enum_args) };
+        let event_ref: rust::Tokens = if service.events.is_empty() {
+            quote! {}
+        } else {
+            quote! {
+                #[doc = $(quoted(format!(" An event pushed by a `{}` server", service.ty_def.name)))]
+                pub struct $(&pascal_name)EventRef<'a, Serv: $(&service_tx) + 'a, $(&generic_tys)> {
+                    msg: <Serv as $(&service_tx)>::BufR<'a>,
+                    #[allow(unused_parens)]
+                    _phantom: core::marker::PhantomData<$(&generic_phantom_args)>
+                }
+
+                impl<'a, S: $(&service_tx)<Ret = $(&ret_enum)> + 'a, $(&generic_tys)> $(&pascal_name)EventRef<'a, S, $(&generic_args)> {
+                    /// Returns [`piton::Error::RxFail`] if the transport handed over a reply rather than an event
+                    fn new(msg: S::BufR<'a>) -> Result<Self, piton::Error> {
+                        use piton::BufR;
+                        if !matches!(msg.view(), $(for event in &service.events join ( | ) => $(&pascal_name)RetRef::$(event.name.to_case(Case::Pascal))(_))) {
+                            return Err(piton::Error::RxFail);
+                        }
+                        Ok(Self {
+                            msg,
+                            _phantom: core::marker::PhantomData,
+                        })
+                    }
+
+                    /// Returns the event's variant of the reply envelope, to match on
+                    pub fn view(&self) -> $(&pascal_name)RetRef<'_, $(&generic_args)> {
+                        use piton::BufR;
+                        self.msg.view()
+                    }
+
+                    /// Returns the trailing region the event's variable-length fields point into
+                    pub fn tail(&self) -> &[u8] {
+                        use piton::BufR;
+                        self.msg.tail()
+                    }
+
+                    $(for event in &service.events =>
+                        #[doc = $(quoted(format!(" Returns the `{}` event, if this is one", event.name.to_case(Case::Snake))))]
+                        pub fn as_$(event.name.to_case(Case::Snake))(&self) -> Option<&$(ty_to_rust(&event.ty))> {
+                            match self.view() {
+                                $(&pascal_name)RetRef::$(event.name.to_case(Case::Pascal))(v) => Some(v),
+                                _ => None,
+                            }
+                        }
+                    )
+                }
+            }
+        };
+
+        let event_methods: rust::Tokens = if service.events.is_empty() {
+            quote! {}
+        } else {
+            quote! {
+                /// Waits for the next event from the server. Returns `None` once the server and its publishers are gone.
+                pub $(flavor.async_()) fn recv_event(&mut self) -> Result<Option<$(&pascal_name)EventRef<'_, T, $(&generic_args)>>, piton::Error> where T: $(&event_rx) {
+                    match <T as $(&event_rx)>::recv_event(&mut self.transport)$(flavor.await_())? {
+                        Some(msg) => $(&pascal_name)EventRef::new(msg).map(Some),
+                        None => Ok(None),
+                    }
+                }
+
+                $(if !self.async_ {
+                    /// Checks for an event without blocking, returning `None` if none has arrived yet
+                    pub fn try_recv_event(&mut self) -> Result<Option<$(&pascal_name)EventRef<'_, T, $(&generic_args)>>, piton::Error> where T: $(&event_rx) {
+                        match <T as $(&event_rx)>::try_recv_event(&mut self.transport)? {
+                            Some(msg) => $(&pascal_name)EventRef::new(msg).map(Some),
+                            None => Ok(None),
+                        }
+                    }
+                })
+            }
+        };
+
         let tokens: rust::Tokens = quote! {
             $(for method in method_structs => $(method))
 
+            $(event_ref)
+
+            $(flavor.allow_async_fn_in_trait())
+            pub trait $(&pascal_name)Calls<$(&generic_tys)> {
+                type Transport: $(&service_tx)<Arg: $(&pascal_name)ReqVariants<$(&generic_args)>, Ret: $(&pascal_name)RetVariants<$(&generic_args)>>;
+
+                $(for method in calls_methods => $(method))
+            }
+
             pub struct $(&pascal_name)Client<T, $(&generic_tys)> {
                 pub transport: T,
                 $(phantom_tys)
             }
 
-            impl<T: piton::ServiceTx, $(&generic_tys)> $(&pascal_name)Client<T, $(&generic_args)> {
+            impl<T: $(&service_tx)<Arg = $(&pascal_name)Req$(&generic_enum_args), Ret = $(&pascal_name)Ret$(&generic_enum_args)>, $(&generic_tys)> $(&pascal_name)Client<T, $(&generic_args)> {
                 pub fn new(transport: T) -> Self {
                     Self {
                         transport,
@@ -364,12 +1507,35 @@ impl crate::ServiceGenerator for ClientGenerator {
                 }
 
                 $(for method in methods => $(method))
+
+                $(event_methods)
             }
+
+            $(for calls_impl in calls_impls => $(calls_impl))
         };
         tokens.to_file_string().into_diagnostic()
     }
 }
 
+/// Returns `hole` followed by a comma if `service` has a header, for the first field of its request variants
+fn header_hole(service: &crate::Service, hole: &str) -> String {
+    match service.header {
+        Some(_) => format!("{}, ", hole),
+        None => String::new(),
+    }
+}
+
+/// Returns the generic args of the `piton::Context` that `service`'s handlers are passed
+fn context_args(service: &crate::Service) -> String {
+    match &service.header {
+        Some(header) => format!("<{}>", ty_to_rust(header)),
+        None => String::new(),
+    }
+}
+
+/// The variant of every service's reply envelope that carries a [`piton::Status`] for requests the server couldn't handle
+const STATUS_VARIANT: &str = "Failed";
+
 pub struct ReqGenerator;
 impl crate::ServiceGenerator for ReqGenerator {
     fn generate_service(&self, service: &crate::Service) -> miette::Result<String> {
@@ -386,30 +1552,173 @@ impl crate::ServiceGenerator for ReqGenerator {
             .methods
             .first()
             .ok_or_else(|| miette!("service must have atleast one method"))?;
+        let header_ty = service.header.as_ref().map(ty_to_rust).unwrap_or_default();
+        if service
+            .methods
+            .iter()
+            .map(|m| &m.name)
+            .chain(service.events.iter().map(|e| &e.name))
+            .any(|name| name.to_case(Case::Pascal) == STATUS_VARIANT)
+        {
+            return Err(miette!(
+                "`{}` can't have a method or event called `{}`, its reply uses that variant for the status",
+                service.ty_def.name,
+                STATUS_VARIANT.to_case(Case::Snake)
+            ));
+        }
+
+        let req_variants: Vec<(String, String)> = service
+            .methods
+            .iter()
+            .map(|m| (m.name.to_case(Case::Pascal), ty_to_rust(&m.arg_ty)))
+            .collect();
+        let ret_variants: Vec<(String, String)> = service
+            .methods
+            .iter()
+            .map(|m| (m.name.to_case(Case::Pascal), ty_to_rust(&m.return_ty)))
+            .chain([(STATUS_VARIANT.to_string(), "piton::Status".to_string())])
+            // Events come after the status, so that declaring one doesn't change the tags of the replies
+            .chain(
+                service
+                    .events
+                    .iter()
+                    .map(|e| (e.name.to_case(Case::Pascal), ty_to_rust(&e.ty))),
+            )
+            .collect();
+
+        let variant_traits: Vec<rust::Tokens> = ["Req", "Ret"]
+            .into_iter()
+            .map(|kind| {
+                let trait_methods: Vec<rust::Tokens> = service
+                    .methods
+                    .iter()
+                    .filter(|m| m.base.is_none())
+                    .map(|method| {
+                        let method_snake = method.name.to_case(Case::Snake);
+                        let ty = ty_to_rust(if kind == "Req" { &method.arg_ty } else { &method.return_ty });
+                        quote! {
+                            fn as_$(&method_snake)(&self) -> Option<&$(&ty)>;
+                            fn as_$(&method_snake)_mut(&mut self) -> Option<&mut $(&ty)>;
+                            fn set_$(&method_snake)(&mut self, msg: $(&ty));
+                            fn ref_as_$(&method_snake)(view: Self::Ref<'_>) -> Option<&$(&ty)>;
+                        }
+                    })
+                    .collect();
+                let impls: Vec<rust::Tokens> = service
+                    .ancestors
+                    .iter()
+                    .map(|base| (base.name.as_str(), generic_args_to_rust(&base.generic_args)))
+                    .chain([(service.ty_def.name.as_str(), generic_tys.to_string().into_diagnostic()?)])
+                    .map(|(owner, owner_args)| {
+                        let impl_methods: Vec<rust::Tokens> = service
+                            .methods
+                            .iter()
+                            .filter(|m| method_owner(service, m) == owner)
+                            .map(|method| {
+                                let method_snake = method.name.to_case(Case::Snake);
+                                let method_pascal = method.name.to_case(Case::Pascal);
+                                let ty = ty_to_rust(if kind == "Req" { &method.arg_ty } else { &method.return_ty });
+                                // Requests hold the service's header ahead of the argument, which setting the argument keeps
+                                let (hole, header) = if kind == "Req" {
+                                    (header_hole(service, "_"), header_hole(service, "core::mem::take(piton::Headed::header_mut(self))"))
+                                } else {
+                                    (String::new(), String::new())
+                                };
+                                quote! {
+                                    #[allow(unreachable_patterns)]
+                                    fn as_$(&method_snake)(&self) -> Option<&$(&ty)> {
+                                        match self {
+                                            Self::$(&method_pascal)($(&hole)v) => Some(v),
+                                            _ => None,
+                                        }
+                                    }
+
+                                    #[allow(unreachable_patterns)]
+                                    fn as_$(&method_snake)_mut(&mut self) -> Option<&mut $(&ty)> {
+                                        match self {
+                                            Self::$(&method_pascal)($(&hole)v) => Some(v),
+                                            _ => None,
+                                        }
+                                    }
+
+                                    fn set_$(&method_snake)(&mut self, msg: $(&ty)) {
+                                        *self = Self::$(&method_pascal)($(&header)msg);
+                                    }
+                                    #[allow(unreachable_patterns)]
+                                    fn ref_as_$(&method_snake)(view: Self::Ref<'_>) -> Option<&$(&ty)> {
+                                        match view {
+                                            $(&pascal_name)$(kind)Ref::$(&method_pascal)(v) => Some(v),
+                                            _ => None,
+                                        }
+                                    }
+                                }
+                            })
+                            .collect();
+                        Ok(quote! {
+                            impl$(&generic_args) $(owner.to_case(Case::Pascal))$(kind)Variants<$(owner_args)> for $(&pascal_name)$(kind)<$(&generic_tys)> {
+                                $(for method in impl_methods => $(method))
+                            }
+                        })
+                    })
+                    .collect::<miette::Result<_>>()?;
+                Ok(quote! {
+                    pub trait $(&pascal_name)$(kind)Variants$(&generic_args): piton::Envelope {
+                        $(for method in trait_methods => $(method))
+                    }
+
+                    $(for i in impls => $(i))
+                })
+            })
+            .collect::<miette::Result<_>>()?;
 
         let tokens: rust::Tokens = quote! {
+            $(for t in variant_traits => $(t))
+
             #[derive(bytecheck::CheckBytes, Clone)]
             #[repr(u32)]
             pub enum $(&pascal_name)Req$(&generic_args) {
-                $(for method in service.methods.iter() => $(method.name.to_case(Case::Pascal))($(ty_to_rust(&method.arg_ty))),)
+                $(for (i, method) in service.methods.iter().enumerate() => $(method.name.to_case(Case::Pascal))($(header_hole(service, &header_ty))$(ty_to_rust(&method.arg_ty))) = $(envelope_tag(i)),)
             }
             impl$(&generic_args) core::default::Default for $(&pascal_name)Req<$(&generic_tys)> {
-                fn default() -> Self { Self::$(first_method.name.to_case(Case::Pascal))(Default::default()) }
+                fn default() -> Self { Self::$(first_method.name.to_case(Case::Pascal))($(header_hole(service, "Default::default()"))Default::default()) }
             }
 
-            unsafe impl$(&generic_args) piton::Yule for $(&pascal_name)Req<$(&generic_tys)> {}
+            unsafe impl$(&generic_args) piton::Yule for $(&pascal_name)Req<$(&generic_tys)> {
+                $(enum_layout_assert(service.header.iter().chain(service.methods.iter().map(|m| &m.arg_ty))))
+            }
 
             #[derive(bytecheck::CheckBytes, Clone)]
             #[repr(u32)]
             pub enum $(&pascal_name)Ret$(&generic_args) {
-                $(for method in service.methods.iter() => $(method.name.to_case(Case::Pascal))($(ty_to_rust(&method.return_ty))),)
+                $(for (i, method) in service.methods.iter().enumerate() => $(method.name.to_case(Case::Pascal))($(ty_to_rust(&method.return_ty))) = $(envelope_tag(i)),)
+                $(STATUS_VARIANT)(piton::Status) = $(envelope_tag(service.methods.len())),
+                $(for (i, event) in service.events.iter().enumerate() => $(event.name.to_case(Case::Pascal))($(ty_to_rust(&event.ty))) = $(envelope_tag(service.methods.len() + 1 + i)),)
             }
 
             impl$(&generic_args) core::default::Default for $(&pascal_name)Ret<$(&generic_tys)> {
                 fn default() -> Self { Self::$(first_method.name.to_case(Case::Pascal))(Default::default()) }
             }
 
-            unsafe impl$(&generic_args) piton::Yule for $(&pascal_name)Ret<$(&generic_tys)> {}
+            unsafe impl$(&generic_args) piton::Yule for $(&pascal_name)Ret<$(&generic_tys)> {
+                $(enum_layout_assert(service.methods.iter().map(|m| &m.return_ty).chain(service.events.iter().map(|e| &e.ty))))
+            }
+
+            impl$(&generic_args) piton::Reply for $(&pascal_name)Ret<$(&generic_tys)> {
+                fn status(view: &Self::Ref<'_>) -> piton::Status {
+                    match view {
+                        $(&pascal_name)RetRef::$(STATUS_VARIANT)(status) => **status,
+                        _ => piton::Status::Ok,
+                    }
+                }
+
+                fn set_status(&mut self, status: piton::Status) {
+                    *self = Self::$(STATUS_VARIANT)(status);
+                }
+            }
+
+            $(generate_envelope(&format!("{}Req", pascal_name), &service.ty_def.generic_tys, &req_variants, service.header.as_ref()))
+
+            $(generate_envelope(&format!("{}Ret", pascal_name), &service.ty_def.generic_tys, &ret_variants, None))
         };
         Ok(tokens.to_file_string().unwrap())
     }
@@ -428,6 +1737,12 @@ impl crate::BusGenerator for MsgGenerator {
         };
         let generic_tys = quote! { $(for t in &bus.ty_def.generic_tys => $(t.to_rust()),) };
 
+        let variants: Vec<(String, String)> = bus
+            .msgs
+            .iter()
+            .map(|m| (m.name.to_case(Case::Pascal), ty_to_rust(&m.ty)))
+            .collect();
+
         let first_msg = bus
             .msgs
             .first()
@@ -437,23 +1752,33 @@ impl crate::BusGenerator for MsgGenerator {
             #[derive(bytecheck::CheckBytes, Clone)]
             #[repr(u32)]
             pub enum $(&pascal_name)Msg$(&generic_args){
-                $(for method in bus.msgs.iter() => $(method.name.to_case(Case::Pascal))($(ty_to_rust(&method.ty))),)
+                $(for (i, method) in bus.msgs.iter().enumerate() => $(method.name.to_case(Case::Pascal))($(ty_to_rust(&method.ty))) = $(envelope_tag(i)),)
             }
 
             impl$(&generic_args) core::default::Default for $(&pascal_name)Msg<$(&generic_tys)> {
                 fn default() -> Self { Self::$(first_msg.name.to_case(Case::Pascal))(Default::default()) }
             }
 
-            unsafe impl$(generic_args) piton::Yule for $(&pascal_name)Msg<$(&generic_tys)> {}
+            unsafe impl$(generic_args) piton::Yule for $(&pascal_name)Msg<$(&generic_tys)> {
+                $(enum_layout_assert(bus.msgs.iter().map(|m| &m.ty)))
+            }
+
+            $(generate_envelope(&format!("{}Msg", pascal_name), &bus.ty_def.generic_tys, &variants, None))
         };
         Ok(tokens.to_file_string().unwrap())
     }
 }
 
-pub struct BusTxGenerator;
+#[derive(Default)]
+pub struct BusTxGenerator {
+    /// Generate an async client for `piton::AsyncBusTx` transports
+    pub async_: bool,
+}
 
 impl crate::BusGenerator for BusTxGenerator {
     fn generate_bus(&self, service: &crate::Bus) -> miette::Result<String> {
+        let flavor = Flavor(self.async_);
+        let bus_tx = flavor.transport("BusTx");
         let mut hasher = DefaultHasher::default();
         hasher.write(service.ty_def.name.as_bytes());
         let pascal_name = service.ty_def.name.to_case(Case::Pascal);
@@ -488,9 +1813,9 @@ impl crate::BusGenerator for BusTxGenerator {
             let method_pascal = method.name.to_case(Case::Pascal);
 
             quote! {
-                pub fn $(method.name.to_case(Case::Snake))_ref(&mut self) -> Result<$(&method_pascal)SendRef<'_, T, $(&generic_args)>, piton::Error> {
+                pub $(flavor.async_()) fn $(method.name.to_case(Case::Snake))_ref(&mut self) -> Result<$(&method_pascal)SendRef<'_, T, $(&generic_args)>, piton::Error> {
                     Ok($(method_pascal)SendRef {
-                        msg: self.transport.alloc()?,
+                        msg: self.transport.alloc()$(flavor.await_())?,
                         transport: &mut self.transport,
                         _phantom: core::marker::PhantomData,
                     })
@@ -507,21 +1832,32 @@ impl crate::BusGenerator for BusTxGenerator {
                 let req_enum = quote! { $(&pascal_name)Msg };
 
                 quote! {
-                    pub struct $(&method_pascal)SendRef<'a, Serv: piton::BusTx + 'a, $(&generic_tys)> {
+                    pub struct $(&method_pascal)SendRef<'a, Serv: $(&bus_tx) + 'a, $(&generic_tys)> {
                         transport: &'a mut Serv,
-                        msg: <Serv as piton::BusTx>::BufW<'a>,
+                        msg: <Serv as $(&bus_tx)>::BufW<'a>,
                         #[allow(unused_parens)]
                         _phantom: core::marker::PhantomData<$(&generic_phantom_args)>
                     }
 
-                    impl<'a, S: piton::BusTx<Msg = $(&req_enum)$(&generic_enum_args)> + 'a, $(&generic_tys)> $(&method_pascal)SendRef<'a, S, $(&generic_args)> {
-                        pub fn send(self) -> Result<(), piton::Error> {
-                            self.transport.send(self.msg)?;
+                    impl<'a, S: $(&bus_tx)<Msg = $(&req_enum)$(&generic_enum_args)> + 'a, $(&generic_tys)> $(&method_pascal)SendRef<'a, S, $(&generic_args)> {
+                        pub $(flavor.async_()) fn send(self) -> Result<(), piton::Error> {
+                            self.transport.send(self.msg)$(flavor.await_())?;
                             Ok(())
                         }
+
+                        /// Calls `f` with the message and a writer for the trailing region its variable-length fields point into
+                        pub fn with_tail<R>(&mut self, f: impl FnOnce(&mut $(ty_to_rust(arg_ty)), piton::types::TailWriter<'_>) -> R) -> R {
+                            use piton::BufW;
+                            self.msg.with_tail(|msg, tail| {
+                                #[allow(irrefutable_let_patterns)]
+                                if let $(&req_enum)::$(&method_pascal)(v) = msg {
+                                    f(v, tail)
+                                } else { unreachable!() }
+                            })
+                        }
                     }
 
-                    impl<'a, S: piton::BusTx<Msg = $(&req_enum)$(&generic_enum_args)> + 'a, $(&generic_tys)> core::ops::Deref for $(&method_pascal)SendRef<'a, S, $(&generic_args)> {
+                    impl<'a, S: $(&bus_tx)<Msg = $(&req_enum)$(&generic_enum_args)> + 'a, $(&generic_tys)> core::ops::Deref for $(&method_pascal)SendRef<'a, S, $(&generic_args)> {
                         type Target = $(ty_to_rust(arg_ty));
 
                         fn deref(&self) -> &Self::Target {
@@ -532,7 +1868,7 @@ impl crate::BusGenerator for BusTxGenerator {
                         }
                     }
 
-                    impl<'a, S: piton::BusTx<Msg = $(&req_enum)$(&generic_enum_args)> + 'a, $(&generic_tys)> core::ops::DerefMut for $(&method_pascal)SendRef<'a, S, $(&generic_args)> {
+                    impl<'a, S: $(&bus_tx)<Msg = $(&req_enum)$(&generic_enum_args)> + 'a, $(&generic_tys)> core::ops::DerefMut for $(&method_pascal)SendRef<'a, S, $(&generic_args)> {
                         fn deref_mut(&mut self) -> &mut Self::Target {
                             #[allow(irrefutable_let_patterns)]
                             if let $(req_enum)::$(&method_pascal)(v) = self.msg.deref_mut() {
@@ -555,7 +1891,7 @@ impl crate::BusGenerator for BusTxGenerator {
                 $(phantom_tys)
             }
 
-            impl<T: piton::BusTx<Msg = $(&pascal_name)Msg$(generic_enum_args)>, $(generic_tys)> $(&pascal_name)Client<T, $(&generic_args)> {
+            impl<T: $(&bus_tx)<Msg = $(&pascal_name)Msg$(generic_enum_args)>, $(generic_tys)> $(&pascal_name)Client<T, $(&generic_args)> {
                 pub fn new(transport: T) -> Self {
                     Self {
                         transport,
@@ -570,16 +1906,22 @@ impl crate::BusGenerator for BusTxGenerator {
     }
 }
 
-pub struct BusRxGenerator;
+#[derive(Default)]
+pub struct BusRxGenerator {
+    /// Generate an async server and receiver for `piton::AsyncBusRx` transports, with async handlers
+    pub async_: bool,
+}
 
 impl crate::BusGenerator for BusRxGenerator {
     fn generate_bus(&self, service: &crate::Bus) -> miette::Result<String> {
+        let flavor = Flavor(self.async_);
+        let bus_rx = flavor.transport("BusRx");
         let pascal_name = service.ty_def.name.to_case(Case::Pascal);
         let mut hasher = DefaultHasher::default();
         hasher.write(service.ty_def.name.as_bytes());
         let trait_methods: Vec<rust::Tokens> = service.msgs.iter().map(|method| {
             quote! {
-                fn $(method.name.to_case(Case::Snake))(&mut self, msg: & $(ty_to_rust(&method.ty))) -> Result<(), piton::Error>;
+                $(flavor.async_()) fn $(method.name.to_case(Case::Snake))(&mut self, msg: & $(ty_to_rust(&method.ty)), tail: &[u8]) -> Result<(), piton::Error>;
             }
         }).collect();
 
@@ -606,8 +1948,8 @@ impl crate::BusGenerator for BusRxGenerator {
             .iter()
             .map(|method| {
                 quote! {
-                    $(&pascal_name)Msg::$(method.name.to_case(Case::Pascal))(arg) => {
-                        self.service.$(method.name.to_case(Case::Snake))(arg)?;
+                    $(&pascal_name)MsgRef::$(method.name.to_case(Case::Pascal))(arg) => {
+                        service.$(method.name.to_case(Case::Snake))(arg, recv.tail())$(flavor.await_())?;
                     }
                 }
             })
@@ -618,7 +1960,8 @@ impl crate::BusGenerator for BusRxGenerator {
         let req_enum = quote! { $(&pascal_name)Msg };
 
         let tokens: rust::Tokens = quote! {
-            pub trait $(&pascal_name)Service<T: piton::BusRx, $(&generic_args)> {
+            $(flavor.allow_async_fn_in_trait())
+            pub trait $(&pascal_name)Service<T: $(&bus_rx), $(&generic_args)> {
                 $(for method in trait_methods => $(method))
             }
 
@@ -628,7 +1971,7 @@ impl crate::BusGenerator for BusRxGenerator {
                 $(phantom_tys.clone())
             }
 
-            impl<T: piton::BusRx<Msg = $(&req_enum)$(&generic_enum_args)>, S: $(&pascal_name)Service<T, $(&generic_args)>, $(&generic_tys)> $(&pascal_name)Server<T, S, $(&generic_args)> {
+            impl<T: $(&bus_rx)<Msg = $(&req_enum)$(&generic_enum_args)>, S: $(&pascal_name)Service<T, $(&generic_args)>, $(&generic_tys)> $(&pascal_name)Server<T, S, $(&generic_args)> {
                 pub fn new(transport: T, service: S) -> Self {
                     Self {
                         transport,
@@ -637,15 +1980,56 @@ impl crate::BusGenerator for BusRxGenerator {
                     }
                 }
 
-                pub fn run(mut self) -> Result<(), piton::Error> {
-                    while let Some(recv) = self.transport.recv()? {
-                        use piton::BufR;
-                        #[allow(clippy::single_match, unreachable_patterns)]
-                        match recv.as_ref() {
-                            $(for arm in match_arms => $(arm))
-                            _ => {}
+                /// Handles messages until the transport is closed
+                pub $(flavor.async_()) fn run(mut self) -> Result<(), piton::Error> {
+                    while let Some(recv) = self.transport.recv()$(flavor.await_())? {
+                        Self::handle(&mut self.service, recv)$(flavor.await_())?;
+                    }
+                    Ok(())
+                }
+
+                $(if self.async_ {
+                    /// Handles messages until the transport is closed or `shutdown` is called
+                    pub async fn run_until(mut self, shutdown: &piton::Shutdown) -> Result<(), piton::Error> {
+                        while let Some(recv) = shutdown.until(self.transport.recv()).await {
+                            let Some(recv) = recv? else {
+                                break;
+                            };
+                            Self::handle(&mut self.service, recv).await?;
+                        }
+                        Ok(())
+                    }
+                } else {
+                    /// Handles messages until the transport is closed or `shutdown` is called
+                    pub fn run_until(mut self, shutdown: &piton::Shutdown) -> Result<(), piton::Error> {
+                        while let Some(recv) = self.transport.recv_until(shutdown)? {
+                            Self::handle(&mut self.service, recv)?;
+                        }
+                        Ok(())
+                    }
+                })
+
+                $(if !self.async_ {
+                    /// Handles at most one message without blocking, returning whether there was one.
+                    /// Lets a superloop interleave the server with other work.
+                    pub fn poll(&mut self) -> Result<bool, piton::Error> {
+                        match self.transport.try_recv()? {
+                            Some(recv) => {
+                                Self::handle(&mut self.service, recv)?;
+                                Ok(true)
+                            }
+                            None => Ok(false),
                         }
                     }
+                })
+
+                $(flavor.async_()) fn handle(service: &mut S, recv: T::BufR<'_>) -> Result<(), piton::Error> {
+                    use piton::BufR;
+                    #[allow(clippy::single_match, unreachable_patterns)]
+                    match recv.view() {
+                        $(for arm in match_arms => $(arm))
+                        _ => {}
+                    }
                     Ok(())
                 }
             }
@@ -657,7 +2041,7 @@ impl crate::BusGenerator for BusRxGenerator {
             }
 
 
-            impl<T: piton::BusRx<Msg = $(&pascal_name)Msg<$(&generic_args)>>, $(&generic_tys)> $(&pascal_name)Rx<T, $(&generic_args)> {
+            impl<T: $(&bus_rx)<Msg = $(&pascal_name)Msg<$(&generic_args)>>, $(&generic_tys)> $(&pascal_name)Rx<T, $(&generic_args)> {
                 pub fn new(transport: T) -> Self {
                     Self {
                         transport,
@@ -665,15 +2049,219 @@ impl crate::BusGenerator for BusRxGenerator {
                     }
                 }
 
-                pub fn recv(&mut self) -> Result<Option<T::BufR<'_>>, piton::Error> {
-                     self.transport.recv()
+                pub $(flavor.async_()) fn recv(&mut self) -> Result<Option<T::BufR<'_>>, piton::Error> {
+                     self.transport.recv()$(flavor.await_())
                 }
+
+                $(if !self.async_ {
+                    /// Checks for a message without blocking, returning `None` if nothing has arrived yet
+                    pub fn try_recv(&mut self) -> Result<Option<T::BufR<'_>>, piton::Error> {
+                        self.transport.try_recv()
+                    }
+                })
             }
         };
         tokens.to_file_string().into_diagnostic()
     }
 }
 
+/// Spells out the parts of generated servers and clients that differ between blocking and async transports
+#[derive(Clone, Copy)]
+struct Flavor(bool);
+
+impl Flavor {
+    /// Returns the path of the transport trait `name`, or of its async version
+    fn transport(self, name: &str) -> String {
+        if self.0 {
+            format!("piton::Async{}", name)
+        } else {
+            format!("piton::{}", name)
+        }
+    }
+
+    fn async_(self) -> &'static str {
+        if self.0 {
+            "async"
+        } else {
+            ""
+        }
+    }
+
+    fn await_(self) -> &'static str {
+        if self.0 {
+            ".await"
+        } else {
+            ""
+        }
+    }
+
+    /// Generated traits with async methods are meant to be implemented, not used as `dyn` or across threads
+    /// by generic code, so the lint about their futures' auto traits doesn't apply
+    fn allow_async_fn_in_trait(self) -> rust::Tokens {
+        if self.0 {
+            quote!(#[allow(async_fn_in_trait)])
+        } else {
+            quote!()
+        }
+    }
+}
+
+/// Generates the [`piton::Envelope`] impl for the envelope enum `name`, along with a `repr(C)` struct
+/// for the tag and payload of each variant, and the `{name}Ref` enum frames are read through.
+///
+/// If there is a `header`, every variant holds it between the tag and the payload, and `name` gets
+/// a `piton::Headed` impl.
+fn generate_envelope(
+    name: &str,
+    generic_tys: &[GenericTy],
+    variants: &[(String, String)],
+    header: Option<&Ty>,
+) -> rust::Tokens {
+    let header = header.map(ty_to_rust);
+    let decl = generic_tys
+        .iter()
+        .map(|t| match t {
+            GenericTy::Ty(t) => format!("{}: piton::Yule", t),
+            t => t.to_rust(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let args = generic_tys
+        .iter()
+        .map(|t| t.name().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (decl_generics, args_generics, ref_decl, ref_args) = if generic_tys.is_empty() {
+        (
+            String::default(),
+            String::default(),
+            "<'a>".to_string(),
+            "<'a>".to_string(),
+        )
+    } else {
+        (
+            format!("<{}>", decl),
+            format!("<{}>", args),
+            format!("<'a, {}>", decl),
+            format!("<'a, {}>", args),
+        )
+    };
+    let frame = |variant: &str| format!("{}{}Frame{}", name, variant, args_generics);
+    // Not every variant uses every type parameter, like the status variant of replies
+    let phantom_args = generic_tys
+        .iter()
+        .filter_map(|t| match t {
+            GenericTy::Ty(t) => Some(format!("{},", t)),
+            GenericTy::Const { .. } => None,
+        })
+        .collect::<String>();
+    quote! {
+        $(for (variant, ty) in variants =>
+            #[allow(dead_code)]
+            #[repr(C)]
+            struct $(name)$(variant)Frame$(&decl_generics) {
+                tag: piton::types::u32le,
+                $(if let Some(header) = &header => header: $(header),)
+                value: $(ty),
+                _phantom: core::marker::PhantomData<($(&phantom_args))>,
+            }
+
+            impl$(&decl_generics) bytecheck::CheckBytes<piton::types::TailContext> for $(frame(variant)) {
+                type Error = <$(ty) as bytecheck::CheckBytes<piton::types::TailContext>>::Error;
+
+                unsafe fn check_bytes<'a>(value: *const Self, context: &mut piton::types::TailContext) -> Result<&'a Self, Self::Error> {
+                    <$(ty) as bytecheck::CheckBytes<piton::types::TailContext>>::check_bytes(core::ptr::addr_of!((*value).value), context)?;
+                    Ok(&*value)
+                }
+            }
+        )
+
+        pub enum $(name)Ref$(&ref_decl) {
+            $(for (variant, ty) in variants => $(variant)(&'a $(ty)),)
+        }
+
+        unsafe impl$(&decl_generics) piton::Envelope for $(name)$(&args_generics) {
+            type Ref<'a> = $(name)Ref$(&ref_args);
+
+            fn frame_len(&self) -> usize {
+                match self {
+                    $(for (variant, _) in variants => Self::$(variant)(..) => core::mem::size_of::<$(frame(variant))>(),)
+                }
+            }
+
+            fn variant_len(tag: u32) -> Option<usize> {
+                match tag {
+                    $(for (i, (variant, _)) in variants.iter().enumerate() => $(i) => Some(core::mem::size_of::<$(frame(variant))>()),)
+                    _ => None,
+                }
+            }
+
+            fn check_frame(frame: &[u8]) -> Result<(), piton::ValidationError> {
+                match Self::tag(frame) {
+                    $(for (i, (variant, _)) in variants.iter().enumerate() => Some($(i)) => Self::check_variant::<$(frame(variant))>(frame, $(quoted(variant))),)
+                    Some(tag) => Err(piton::ValidationError::UnknownTag(tag)),
+                    None => Err(piton::ValidationError::Length { expected: 4, found: frame.len() }),
+                }$(if let Some(header) = &header {
+                    ?;
+                    // `check_variant` checked that the frame is long enough to hold the header
+                    <$(header) as piton::Yule>::check(&frame[<Self as piton::Headed>::HEADER_OFFSET..])
+                        .map(drop)
+                        .map_err(|e| e.within(piton::PathSegment::Field("header")))
+                })
+            }
+
+            unsafe fn view_unchecked(frame: &[u8]) -> Self::Ref<'_> {
+                match Self::tag(frame) {
+                    $(for (i, (variant, _)) in variants.iter().enumerate() => Some($(i)) => $(name)Ref::$(variant)(&Self::variant_unchecked::<$(frame(variant))>(frame).value),)
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        $(if let Some(header) = &header {
+            impl$(&decl_generics) piton::Headed for $(name)$(&args_generics) {
+                type Header = $(header);
+
+                const HEADER_OFFSET: usize = core::mem::offset_of!($(frame(&variants[0].0)), header);
+
+                fn header(&self) -> &$(header) {
+                    match self {
+                        $(for (variant, _) in variants => Self::$(variant)(header, _) => header,)
+                    }
+                }
+
+                fn header_mut(&mut self) -> &mut $(header) {
+                    match self {
+                        $(for (variant, _) in variants => Self::$(variant)(header, _) => header,)
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Returns the discriminant of the `i`th variant of an envelope, which is stored little-endian whatever the target
+fn envelope_tag(i: usize) -> String {
+    format!("{}u32.to_le()", i)
+}
+
+/// Returns the name of the service that declared `method`
+fn method_owner<'a>(service: &'a crate::Service, method: &'a crate::Method) -> &'a str {
+    method
+        .base
+        .as_ref()
+        .map(|b| b.name.as_str())
+        .unwrap_or(&service.ty_def.name)
+}
+
+fn generic_args_to_rust(generic_args: &[GenericArg]) -> String {
+    generic_args
+        .iter()
+        .map(GenericArg::to_rust)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 fn phantom_tys(generic_tys: &[GenericTy]) -> Vec<rust::Tokens> {
     generic_tys
         .iter()
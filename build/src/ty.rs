@@ -1,14 +1,25 @@
 use miette::{miette, IntoDiagnostic, WrapErr};
 use std::{alloc::Layout, collections::HashMap};
 
-use crate::{Expr, GenericTy, Ty};
+use crate::{
+    BitfieldMember, BitfieldTy, BitfieldUnit, Endian, Expr, Field, Flags, GenericArg, GenericTy,
+    Method, Service, ServiceRef, Struct, Ty,
+};
 
 #[derive(Default)]
 pub struct TyChecker {
     known_tys: HashMap<String, Expr>,
+    endian: Endian,
 }
 
 impl TyChecker {
+    pub(crate) fn new(endian: Endian) -> Self {
+        TyChecker {
+            endian,
+            ..Default::default()
+        }
+    }
+
     pub(crate) fn visit_expr(&mut self, expr: &Expr) {
         match expr {
             Expr::Bus(_) | Expr::Service(_) => return,
@@ -22,15 +33,197 @@ impl TyChecker {
         let generic_tys = expr.ty_def().generic_tys.clone();
         let tys = expr.field_tys();
         for (name, ty) in tys {
-            self.resolve_ty(ty, &generic_tys)
+            self.resolve_ty(ty, &generic_tys, &mut vec![])
                 .wrap_err(format!("{} error", name))?;
         }
+        if let Expr::Flags(f) = expr {
+            check_flags(f).wrap_err(format!("{} error", f.ty_def.name))?;
+        }
         Ok(())
     }
 
-    fn resolve_ty(&self, ty: &mut Ty, generic_tys: &[GenericTy]) -> miette::Result<()> {
+    /// Returns whether `ty` is generated as a type that implements `Ord`, looking through aliases
+    fn is_ord(&self, ty: &Ty) -> bool {
+        match ty {
+            Ty::Array { ty, .. } => self.is_ord(ty),
+            Ty::Unresolved { name, .. } => match self.known_tys.get(name.as_str()) {
+                Some(Expr::Alias(a)) => self.is_ord(&a.ty),
+                _ => false,
+            },
+            Ty::String(_)
+            | Ty::Str
+            | Ty::Bytes
+            | Ty::Slice(_)
+            | Ty::Option(_)
+            | Ty::Vec { .. }
+            | Ty::Map { .. }
+            | Ty::Set { .. } => false,
+            _ => true,
+        }
+    }
+
+    /// Returns the layout of `ty`, looking through aliases and newtypes
+    pub(crate) fn layout(&self, ty: &Ty) -> Layout {
         match ty {
-            Ty::Array { ty, .. } => self.resolve_ty(ty, generic_tys),
+            Ty::Array { ty, len } => {
+                let layout = self.layout(ty);
+                Layout::from_size_align(layout.size() * len, layout.align()).expect("bad layout")
+            }
+            Ty::Vec { ty, len } | Ty::Set { ty, len } => vec_layout(self.layout(ty), *len),
+            Ty::Map { key, value, len } => {
+                vec_layout(entry_layout(self.layout(key), self.layout(value)).0, *len)
+            }
+            Ty::Option(ty) => option_layout(self.layout(ty)),
+            Ty::Unresolved { name, .. } => match self.known_tys.get(name.as_str()) {
+                Some(Expr::Alias(a)) => self.layout(&a.ty),
+                Some(Expr::Newtype(n)) => self.layout(&n.ty),
+                Some(Expr::Flags(f)) => self.layout(&f.ty),
+                _ => ty.layout(),
+            },
+            _ => ty.layout(),
+        }
+    }
+
+    /// Packs each run of bitfield members in `s` into integer fields, starting a new
+    /// integer whenever the next member wouldn't fit in 64 bits
+    pub(crate) fn pack_bitfields(&self, s: &mut Struct) -> miette::Result<()> {
+        let mut fields = vec![];
+        let mut run = vec![];
+        for field in std::mem::take(&mut s.fields) {
+            let Some(width) = field.bits else {
+                flush_bitfields(s, self.endian, &mut fields, &mut run);
+                fields.push(field);
+                continue;
+            };
+            let ty = self
+                .bitfield_ty(&field.ty, width)
+                .wrap_err(format!("{} error", field.name))?;
+            let mut offset = run
+                .last()
+                .map(|m: &BitfieldMember| m.offset + m.width)
+                .unwrap_or(0);
+            if offset + width > 64 {
+                flush_bitfields(s, self.endian, &mut fields, &mut run);
+                offset = 0;
+            }
+            run.push(BitfieldMember {
+                name: field.name,
+                ty,
+                offset,
+                width,
+            });
+        }
+        flush_bitfields(s, self.endian, &mut fields, &mut run);
+        s.fields = fields;
+        Ok(())
+    }
+
+    fn bitfield_ty(&self, ty: &Ty, width: usize) -> miette::Result<BitfieldTy> {
+        if width == 0 || width > 64 {
+            return Err(miette!(
+                "bitfield members must be between 1 and 64 bits wide"
+            ));
+        }
+        let max_width = match (ty, ty.uint_width()) {
+            (Ty::Bool, _) => return Ok(BitfieldTy::Bool),
+            (_, Some(max_width)) => max_width,
+            (Ty::Unresolved { name, generic_args }, _) if generic_args.is_empty() => {
+                let Some(Expr::Enum(e)) = self.known_tys.get(name.as_str()) else {
+                    return Err(miette!("{} can't be used as a bitfield member", name));
+                };
+                if !e.ty_def.generic_tys.is_empty() || e.variants.iter().any(|v| v.ty.is_some()) {
+                    return Err(miette!(
+                        "only enums without generics or variant data can be bitfield members"
+                    ));
+                }
+                if width < 64 && e.variants.len() > 1 << width {
+                    return Err(miette!(
+                        "{} has {} variants, which don't fit in {} bits",
+                        name,
+                        e.variants.len(),
+                        width
+                    ));
+                }
+                return Ok(BitfieldTy::Enum {
+                    name: name.clone(),
+                    variants: e.variants.iter().map(|v| v.name.clone()).collect(),
+                });
+            }
+            _ => {
+                return Err(miette!(
+                    "bitfield members must be bools, unsigned integers or fieldless enums"
+                ))
+            }
+        };
+        if width > max_width {
+            return Err(miette!(
+                "{} bits don't fit in a {} bit integer",
+                width,
+                max_width
+            ));
+        }
+        Ok(BitfieldTy::Uint(ty.clone()))
+    }
+
+    fn resolve_ty(
+        &self,
+        ty: &mut Ty,
+        generic_tys: &[GenericTy],
+        aliases: &mut Vec<String>,
+    ) -> miette::Result<()> {
+        match ty {
+            Ty::Array { ty, .. } | Ty::Slice(ty) => self.resolve_ty(ty, generic_tys, aliases),
+            Ty::Option(ty) => {
+                self.resolve_ty(ty, generic_tys, aliases)?;
+                if self.layout(ty).align() > 8 {
+                    return Err(miette!("option values must be at most 8 byte aligned"));
+                }
+                Ok(())
+            }
+            Ty::Vec { ty, len } => {
+                self.resolve_ty(ty, generic_tys, aliases)?;
+                check_vec_layout(self.layout(ty), *len)
+            }
+            Ty::Set { ty, len } => {
+                self.resolve_ty(ty, generic_tys, aliases)?;
+                if !self.is_ord(ty) {
+                    return Err(miette!(
+                        "set items must be integers, floats, bools, chars or arrays of them"
+                    ));
+                }
+                check_vec_layout(self.layout(ty), *len)
+            }
+            Ty::Map { key, value, len } => {
+                self.resolve_ty(key, generic_tys, aliases)?;
+                self.resolve_ty(value, generic_tys, aliases)?;
+                if !self.is_ord(key) {
+                    return Err(miette!(
+                        "map keys must be integers, floats, bools, chars or arrays of them"
+                    ));
+                }
+                let (layout, pad) = entry_layout(self.layout(key), self.layout(value));
+                if pad > 0 {
+                    return Err(miette!(
+                        "map entries need {} bytes of padding between their key and value, try reordering or resizing them",
+                        pad
+                    ));
+                }
+                check_vec_layout(layout, *len)
+            }
+            Ty::NonZero(inner) => {
+                self.resolve_ty(inner, generic_tys, aliases)?;
+                match **inner {
+                    Ty::U128 | Ty::U64 | Ty::U32 | Ty::U16 | Ty::U8 => Ok(()),
+                    Ty::I128 | Ty::I64 | Ty::I32 | Ty::I16 | Ty::I8 => Ok(()),
+                    Ty::U128Be | Ty::U64Be | Ty::U32Be | Ty::U16Be => Ok(()),
+                    Ty::I128Be | Ty::I64Be | Ty::I32Be | Ty::I16Be => Ok(()),
+                    _ => Err(miette!("only integers can be nonzero")),
+                }
+            }
+            Ty::String(len) if !len.is_multiple_of(8) => Err(miette!(
+                "string capacity must be a multiple of 8, try string<{}>",
+                len.div_ceil(8) * 8
+            )),
             Ty::Unresolved { name, generic_args } => {
                 if generic_tys.iter().any(|t| t.name() == name) {
                     if !generic_args.is_empty() {
@@ -53,12 +246,32 @@ impl TyChecker {
                 for arg in generic_args.iter_mut() {
                     match arg {
                         crate::GenericArg::Ty(ty) => {
-                            self.resolve_ty(ty, generic_tys)?;
+                            self.resolve_ty(ty, generic_tys, aliases)?;
                         }
                         crate::GenericArg::Const(_) => {}
                     }
                 }
 
+                if let Expr::Alias(a) = resolved_ty {
+                    if aliases.contains(name) {
+                        return Err(miette!("type alias {} is recursive", name));
+                    }
+                    let map = a
+                        .ty_def
+                        .generic_tys
+                        .iter()
+                        .map(|t| t.name())
+                        .zip(generic_args.iter().cloned())
+                        .collect::<HashMap<_, _>>();
+                    aliases.push(name.clone());
+                    let mut aliased = a.ty.clone();
+                    aliased.substitute(&map);
+                    self.resolve_ty(&mut aliased, generic_tys, aliases)?;
+                    aliases.pop();
+                    *ty = aliased;
+                    return Ok(());
+                }
+
                 if let Expr::Extern(e) = resolved_ty {
                     let map = e
                         .ty_def
@@ -89,6 +302,241 @@ impl TyChecker {
     }
 }
 
+/// Flattens service inheritance, copying every inherited method and header into the services that extend it.
+/// Events belong to the service that declares them, so they aren't copied.
+#[derive(Default)]
+pub struct ServiceChecker {
+    services: HashMap<String, Service>,
+}
+
+impl ServiceChecker {
+    pub(crate) fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Service(s) = expr {
+            self.services.insert(s.ty_def.name.clone(), s.clone());
+        }
+    }
+
+    pub(crate) fn resolve_expr(&self, expr: &mut Expr) -> miette::Result<()> {
+        let Expr::Service(service) = expr else {
+            return Ok(());
+        };
+        let mut ancestors = vec![];
+        let mut methods = vec![];
+        self.collect_bases(
+            &service.bases,
+            &HashMap::default(),
+            &mut vec![service.ty_def.name.clone()],
+            &mut ancestors,
+            &mut methods,
+        )
+        .wrap_err(format!("{} error", service.ty_def.name))?;
+        methods.append(&mut service.methods);
+        for (i, method) in methods.iter().enumerate() {
+            if let Some(dup) = methods[..i].iter().find(|m| m.name == method.name) {
+                let origin = |m: &Method| {
+                    m.base
+                        .as_ref()
+                        .map(|b| b.name.clone())
+                        .unwrap_or_else(|| service.ty_def.name.clone())
+                };
+                return Err(miette!(
+                    "{} defines method {} more than once, from {} and {}",
+                    service.ty_def.name,
+                    method.name,
+                    origin(dup),
+                    origin(method)
+                ));
+            }
+        }
+        for (i, event) in service.events.iter().enumerate() {
+            if service.events[..i].iter().any(|e| e.name == event.name) {
+                return Err(miette!(
+                    "{} defines event {} more than once",
+                    service.ty_def.name,
+                    event.name
+                ));
+            }
+            if methods.iter().any(|m| m.name == event.name) {
+                return Err(miette!(
+                    "{} defines both a method and an event called {}",
+                    service.ty_def.name,
+                    event.name
+                ));
+            }
+        }
+        let header = self.header(service, &HashMap::default());
+        for ancestor in &ancestors {
+            let def = &self.services[&ancestor.name];
+            if self.header(def, &generic_map(def, ancestor)) != header {
+                return Err(miette!(
+                    "{} and its base {} must have the same header",
+                    service.ty_def.name,
+                    ancestor.name
+                ));
+            }
+        }
+        service.ancestors = ancestors;
+        service.header = header;
+        service.methods = methods;
+        Ok(())
+    }
+
+    /// Returns the header `def` declares or inherits, with `generics` substituted into it.
+    ///
+    /// Must only be called once `collect_bases` has checked that `def` doesn't extend itself.
+    fn header(&self, def: &Service, generics: &HashMap<&str, GenericArg>) -> Option<Ty> {
+        let mut header = def.header.clone().or_else(|| {
+            def.bases.iter().find_map(|base| {
+                let base_def = self.services.get(&base.name)?;
+                self.header(base_def, &generic_map(base_def, base))
+            })
+        })?;
+        header.substitute(generics);
+        Some(header)
+    }
+
+    fn collect_bases(
+        &self,
+        bases: &[ServiceRef],
+        generics: &HashMap<&str, GenericArg>,
+        stack: &mut Vec<String>,
+        ancestors: &mut Vec<ServiceRef>,
+        methods: &mut Vec<Method>,
+    ) -> miette::Result<()> {
+        for base in bases {
+            let mut base = base.clone();
+            for arg in &mut base.generic_args {
+                arg.substitute(generics);
+            }
+            if stack.contains(&base.name) {
+                return Err(miette!("service {} extends itself", base.name));
+            }
+            let Some(def) = self.services.get(&base.name) else {
+                return Err(miette!("unknown service {}", base.name));
+            };
+            if def.ty_def.generic_tys.len() != base.generic_args.len() {
+                return Err(miette!(
+                    "{} expects {} generic args not {}",
+                    base.name,
+                    def.ty_def.generic_tys.len(),
+                    base.generic_args.len()
+                ));
+            }
+            if let Some(existing) = ancestors.iter().find(|a| a.name == base.name) {
+                if existing != &base {
+                    return Err(miette!(
+                        "{} is extended more than once with different generic args",
+                        base.name
+                    ));
+                }
+                continue;
+            }
+            let base_generics = generic_map(def, &base);
+            stack.push(base.name.clone());
+            self.collect_bases(&def.bases, &base_generics, stack, ancestors, methods)?;
+            stack.pop();
+            for method in &def.methods {
+                let mut method = method.clone();
+                method.arg_ty.substitute(&base_generics);
+                method.return_ty.substitute(&base_generics);
+                method.base = Some(base.clone());
+                methods.push(method);
+            }
+            ancestors.push(base);
+        }
+        Ok(())
+    }
+}
+
+/// Maps the names of `def`'s generic params to the args `base` gives them
+fn generic_map<'a>(def: &'a Service, base: &ServiceRef) -> HashMap<&'a str, GenericArg> {
+    def.ty_def
+        .generic_tys
+        .iter()
+        .map(|t| t.name())
+        .zip(base.generic_args.iter().cloned())
+        .collect()
+}
+
+fn flush_bitfields(
+    s: &mut Struct,
+    endian: Endian,
+    fields: &mut Vec<Field>,
+    run: &mut Vec<BitfieldMember>,
+) {
+    let Some(last) = run.last() else {
+        return;
+    };
+    let ty = endian.apply(match last.offset + last.width {
+        0..=8 => Ty::U8,
+        9..=16 => Ty::U16,
+        17..=32 => Ty::U32,
+        _ => Ty::U64,
+    });
+    let index = s.bitfields.len();
+    let field = format!("_bitfield{}", index);
+    fields.push(Field {
+        name: field.clone(),
+        ty: ty.clone(),
+        bits: None,
+    });
+    s.bitfields.push(BitfieldUnit {
+        field,
+        ty_name: format!("{}Bitfield{}", s.ty_def.name, index),
+        ty,
+        members: std::mem::take(run),
+    });
+}
+
+/// A vec is an 8 byte length, followed by its elements
+fn vec_layout(elem: Layout, len: usize) -> Layout {
+    Layout::from_size_align(8 + elem.size() * len, 8).expect("bad layout")
+}
+
+fn check_vec_layout(elem: Layout, len: usize) -> miette::Result<()> {
+    if elem.align() > 8 || !(elem.size() * len).is_multiple_of(8) {
+        return Err(miette!(
+            "vec elements must be at most 8 byte aligned and fill a multiple of 8 bytes"
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the layout of a map entry, along with how many bytes of padding it needs
+fn entry_layout(key: Layout, value: Layout) -> (Layout, usize) {
+    let (layout, _) = key.extend(value).expect("bad layout");
+    let layout = layout.pad_to_align();
+    (layout, layout.size() - key.size() - value.size())
+}
+
+/// An option is an 8 byte tag followed by its value, which is at most 8 byte aligned
+fn option_layout(value: Layout) -> Layout {
+    Layout::from_size_align(8 + value.size(), value.align()).expect("bad layout")
+}
+
+fn check_flags(flags: &Flags) -> miette::Result<()> {
+    let Some(width) = flags.ty.uint_width() else {
+        return Err(miette!("flags must be backed by an unsigned integer"));
+    };
+    for (i, flag) in flags.flags.iter().enumerate() {
+        if flag.bit >= width {
+            return Err(miette!(
+                "flag {} uses bit {}, but the backing integer only has {} bits",
+                flag.name,
+                flag.bit,
+                width
+            ));
+        }
+        if let Some(dup) = flags.flags[..i]
+            .iter()
+            .find(|f| f.name == flag.name || f.bit == flag.bit)
+        {
+            return Err(miette!("flags {} and {} overlap", dup.name, flag.name));
+        }
+    }
+    Ok(())
+}
+
 pub struct LayoutChecker {
     layout: Layout,
 }
@@ -115,30 +563,359 @@ impl LayoutChecker {
     }
 
     pub fn final_pad(&self) -> usize {
-        let align = 8;
+        let align = self.layout.align().max(8);
         let len = self.layout.size();
         let len_rounded_up = len.wrapping_add(align).wrapping_sub(1) & !align.wrapping_sub(1);
         len_rounded_up.wrapping_sub(len)
     }
 }
 
+impl GenericArg {
+    /// Replaces any generic parameter named in `generics` with its argument
+    pub(crate) fn substitute(&mut self, generics: &HashMap<&str, GenericArg>) {
+        match self {
+            GenericArg::Ty(Ty::Unresolved { name, generic_args }) if generic_args.is_empty() => {
+                if let Some(arg) = generics.get(name.as_str()) {
+                    *self = arg.clone();
+                }
+            }
+            GenericArg::Ty(ty) => ty.substitute(generics),
+            GenericArg::Const(_) => {}
+        }
+    }
+}
+
 impl Ty {
+    /// Replaces any generic parameter named in `generics` with its argument
+    pub(crate) fn substitute(&mut self, generics: &HashMap<&str, GenericArg>) {
+        match self {
+            Ty::Array { ty, .. }
+            | Ty::Vec { ty, .. }
+            | Ty::Set { ty, .. }
+            | Ty::Option(ty)
+            | Ty::Slice(ty) => ty.substitute(generics),
+            Ty::Map { key, value, .. } => {
+                key.substitute(generics);
+                value.substitute(generics);
+            }
+            Ty::Unresolved { name, generic_args } => {
+                if generic_args.is_empty() {
+                    if let Some(GenericArg::Ty(ty)) = generics.get(name.as_str()) {
+                        *self = ty.clone();
+                        return;
+                    }
+                }
+                for arg in generic_args {
+                    arg.substitute(generics);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the width in bits of unsigned integer types, regardless of their byte order
+    pub(crate) fn uint_width(&self) -> Option<usize> {
+        match self {
+            Ty::U8 => Some(8),
+            Ty::U16 | Ty::U16Be => Some(16),
+            Ty::U32 | Ty::U32Be => Some(32),
+            Ty::U64 | Ty::U64Be => Some(64),
+            _ => None,
+        }
+    }
+
     pub fn layout(&self) -> Layout {
         match self {
-            Ty::U64 => Layout::new::<u64>(),
-            Ty::U32 => Layout::new::<u32>(),
-            Ty::U16 => Layout::new::<u16>(),
-            Ty::U8 => Layout::new::<u16>(),
-            Ty::I64 => Layout::new::<i64>(),
-            Ty::I32 => Layout::new::<i32>(),
-            Ty::I16 => Layout::new::<i16>(),
-            Ty::I8 => Layout::new::<i16>(),
-            Ty::F64 => Layout::new::<f64>(),
-            Ty::F32 => Layout::new::<f32>(),
+            Ty::U64 | Ty::U64Be => Layout::new::<u64>(),
+            Ty::U32 | Ty::U32Be => Layout::new::<u32>(),
+            Ty::U16 | Ty::U16Be => Layout::new::<u16>(),
+            Ty::U8 => Layout::new::<u8>(),
+            Ty::I64 | Ty::I64Be => Layout::new::<i64>(),
+            Ty::I32 | Ty::I32Be => Layout::new::<i32>(),
+            Ty::I16 | Ty::I16Be => Layout::new::<i16>(),
+            Ty::I8 => Layout::new::<i8>(),
+            Ty::F64 | Ty::F64Be => Layout::new::<f64>(),
+            Ty::F32 | Ty::F32Be => Layout::new::<f32>(),
             Ty::Bool => Layout::new::<bool>(),
-            Ty::Array { ty, .. } => ty.layout(),
+            Ty::Char | Ty::CharBe => Layout::new::<u32>(),
+            // the wrappers are always 16 byte aligned, even on targets where u128 isn't
+            Ty::U128 | Ty::U128Be | Ty::I128 | Ty::I128Be => {
+                Layout::from_size_align(16, 16).expect("bad layout")
+            }
+            Ty::NonZero(ty) => ty.layout(),
+            Ty::String(len) => Layout::from_size_align(8 + len, 8).expect("bad layout"),
+            Ty::Vec { ty, len } | Ty::Set { ty, len } => vec_layout(ty.layout(), *len),
+            Ty::Map { key, value, len } => {
+                vec_layout(entry_layout(key.layout(), value.layout()).0, *len)
+            }
+            Ty::Option(ty) => option_layout(ty.layout()),
+            // an offset and a length
+            Ty::Str | Ty::Bytes | Ty::Slice(_) => {
+                Layout::from_size_align(8, 4).expect("bad layout")
+            }
+            Ty::Array { ty, len } => {
+                Layout::from_size_align(ty.layout().size() * len, ty.layout().align())
+                    .expect("bad layout")
+            }
             Ty::Unresolved { .. } => Layout::from_size_align(8, 8).expect("bad layout"),
             Ty::Extern(_) => Layout::from_size_align(8, 8).expect("bad layout"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piton_parser;
+
+    fn parse(src: &str) -> (Endian, Vec<Expr>) {
+        let endian = piton_parser::endian(src, Endian::Little).unwrap_or_default();
+        (endian, piton_parser::exprs(src, endian).unwrap())
+    }
+
+    fn services(src: &str) -> miette::Result<Vec<Service>> {
+        let (_, mut exprs) = parse(src);
+        let mut checker = ServiceChecker::default();
+        for expr in &exprs {
+            checker.visit_expr(expr);
+        }
+        for expr in &mut exprs {
+            checker.resolve_expr(expr)?;
+        }
+        Ok(exprs
+            .into_iter()
+            .filter_map(|e| match e {
+                Expr::Service(s) => Some(s),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn tys(src: &str) -> miette::Result<(TyChecker, Vec<Expr>)> {
+        let (endian, mut exprs) = parse(src);
+        let mut checker = TyChecker::new(endian);
+        for expr in &exprs {
+            checker.visit_expr(expr);
+        }
+        for expr in &mut exprs {
+            checker.resolve_expr(expr)?;
+        }
+        Ok((checker, exprs))
+    }
+
+    /// Joins the messages of `err` and everything it wraps
+    fn message(err: miette::Report) -> String {
+        err.chain()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(": ")
+    }
+
+    #[test]
+    fn services_inherit_their_bases_methods_first() {
+        let services = services(
+            "service A { method a(X) -> X }
+            service B: A { method b(X) -> X }
+            service C: B + A { method c(X) -> X }",
+        )
+        .unwrap();
+        let c = &services[2];
+        let names = c
+            .methods
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c"]);
+        let bases = c
+            .methods
+            .iter()
+            .map(|m| m.base.as_ref().map(|b| b.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(bases, [Some("A"), Some("B"), None]);
+        let ancestors = c
+            .ancestors
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ancestors, ["A", "B"]);
+    }
+
+    #[test]
+    fn inherited_methods_use_the_bases_generic_args() {
+        let services = services(
+            "service A<T> { method a(T) -> T }
+            service B: A<u8> { method b(X) -> X }",
+        )
+        .unwrap();
+        assert_eq!(services[1].methods[0].arg_ty, Ty::U8);
+    }
+
+    #[test]
+    fn rejects_cycles_unknown_bases_and_duplicate_methods() {
+        let err = |src| {
+            let err = services(src).map(drop).unwrap_err();
+            err.chain()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(": ")
+        };
+        assert!(err("service A: B {}\nservice B: A {}").contains("service A extends itself"));
+        assert!(err("service A: Z {}").contains("unknown service Z"));
+        assert!(
+            err("service A { method a(X) -> X }\nservice B: A { method a(X) -> X }")
+                .contains("B defines method a more than once, from A and B")
+        );
+    }
+
+    #[test]
+    fn aliases_are_replaced_by_what_they_alias() {
+        let (_, exprs) =
+            tys("type Id = u32;\ntype Pair<T> = [T; 2];\nstruct S { id: Id, ids: Pair<Id> }")
+                .unwrap();
+        let Expr::Struct(s) = &exprs[2] else {
+            panic!("expected a struct")
+        };
+        assert_eq!(s.fields[0].ty, Ty::U32);
+        let pair = Ty::Array {
+            ty: Box::new(Ty::U32),
+            len: 2,
+        };
+        assert_eq!(s.fields[1].ty, pair);
+    }
+
+    #[test]
+    fn newtypes_are_laid_out_like_what_they_wrap() {
+        let (checker, exprs) = tys("newtype Millis(u64);\nstruct S { ms: Millis }").unwrap();
+        let Expr::Struct(s) = &exprs[1] else {
+            panic!("expected a struct")
+        };
+        assert!(matches!(&s.fields[0].ty, Ty::Unresolved { name, .. } if name == "Millis"));
+        assert_eq!(checker.layout(&s.fields[0].ty), Layout::new::<u64>());
+    }
+
+    #[test]
+    fn rejects_recursive_aliases() {
+        let err = tys("type A = B;\ntype B = A;\nstruct S { a: A }").map(drop);
+        assert!(message(err.unwrap_err()).contains("is recursive"));
+    }
+
+    #[test]
+    fn rejects_flags_that_dont_fit_or_overlap() {
+        let err = |src| message(tys(src).map(drop).unwrap_err());
+        assert!(err("flags F: u8 { A = 8 }").contains("flag A uses bit 8"));
+        assert!(err("flags F: u8 { A = 1, B = 1 }").contains("flags A and B overlap"));
+        assert!(err("flags F: i8 { A = 1 }").contains("unsigned integer"));
+        assert!(tys("flags F: u64 { A = 0, B = 63 }").is_ok());
+    }
+
+    fn packed(src: &str) -> miette::Result<Struct> {
+        let (checker, exprs) = tys(src)?;
+        let Some(Expr::Struct(mut s)) = exprs.into_iter().last() else {
+            panic!("expected a struct")
+        };
+        checker.pack_bitfields(&mut s)?;
+        Ok(s)
+    }
+
+    #[test]
+    fn packs_runs_of_bitfield_members_into_integers() {
+        let s = packed("struct S { a: u8:3, b: bool:1, c: u32, d: u64:60, e: u8:5 }").unwrap();
+        let fields = s
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.ty.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("_bitfield0", Ty::U8),
+                ("c", Ty::U32),
+                ("_bitfield1", Ty::U64),
+                ("_bitfield2", Ty::U8)
+            ]
+        );
+        let members = |i: usize| {
+            s.bitfields[i]
+                .members
+                .iter()
+                .map(|m| (m.name.as_str(), m.offset, m.width))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(members(0), [("a", 0, 3), ("b", 3, 1)]);
+        assert_eq!(members(2), [("e", 0, 5)]);
+    }
+
+    #[test]
+    fn rejects_bitfield_members_that_dont_fit() {
+        let err = |src| message(packed(src).map(drop).unwrap_err());
+        assert!(err("struct S { a: u8:9 }").contains("9 bits don't fit in a 8 bit integer"));
+        assert!(err("struct S { a: u8:0 }").contains("between 1 and 64 bits"));
+        assert!(err("struct S { a: i8:2 }").contains("unsigned integers"));
+        let speed = "enum Speed { Stop, Slow, Fast }\n";
+        assert!(err(&format!("{speed}struct S {{ a: Speed:1 }}")).contains("don't fit in 1 bits"));
+        assert!(packed(&format!("{speed}struct S {{ a: Speed:2 }}")).is_ok());
+    }
+
+    #[test]
+    fn schemas_can_default_to_big_endian() {
+        let tys = |src| {
+            let s = packed(src).unwrap();
+            s.fields.iter().map(|f| f.ty.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            tys("struct S { a: u32, b: u16be, c: u8, d: u8:4, e: u16:12 }"),
+            [Ty::U32, Ty::U16Be, Ty::U8, Ty::U16]
+        );
+        assert_eq!(
+            tys("#![endian(big)]\nstruct S { a: u32, b: u16le, c: u8, d: u8:4, e: u16:12 }"),
+            [Ty::U32Be, Ty::U16, Ty::U8, Ty::U16Be]
+        );
+    }
+
+    #[test]
+    fn only_integers_can_be_nonzero() {
+        assert!(tys("struct S { a: nonzero u32, b: nonzero i128be }").is_ok());
+        let err = tys("struct S { a: nonzero char }").map(drop).unwrap_err();
+        assert!(message(err).contains("only integers can be nonzero"));
+        let (_, exprs) = tys("type NodeId = u32; struct S { a: nonzero NodeId }").unwrap();
+        let Expr::Struct(s) = &exprs[1] else {
+            panic!("expected a struct")
+        };
+        assert_eq!(s.fields[0].ty, Ty::NonZero(Box::new(Ty::U32)));
+        let err = tys("newtype Id(u32); struct S { a: nonzero Id }")
+            .map(drop)
+            .unwrap_err();
+        assert!(message(err).contains("only integers can be nonzero"));
+    }
+
+    #[test]
+    fn string_capacities_must_be_multiples_of_8() {
+        assert!(tys("struct S { a: string<16> }").is_ok());
+        let err = tys("struct S { a: string<10> }").map(drop).unwrap_err();
+        assert!(message(err).contains("try string<16>"));
+    }
+
+    #[test]
+    fn vecs_must_not_need_padding() {
+        assert!(tys("struct S { a: vec<u16, 4>, b: vec<u64, 1> }").is_ok());
+        let err = tys("struct S { a: vec<u16, 3> }").map(drop).unwrap_err();
+        assert!(message(err).contains("fill a multiple of 8 bytes"));
+        assert!(tys("struct S { a: vec<u128, 1> }").is_err());
+    }
+
+    #[test]
+    fn option_values_must_be_at_most_8_byte_aligned() {
+        assert!(tys("struct S { a: u64?, b: option<u8> }").is_ok());
+        let err = tys("struct S { a: u128? }").map(drop).unwrap_err();
+        assert!(message(err).contains("at most 8 byte aligned"));
+    }
+
+    #[test]
+    fn map_entries_must_not_need_padding() {
+        assert!(tys("struct S { a: map<u32, u32, 2>, b: set<u16, 4> }").is_ok());
+        let err = tys("struct S { a: map<u8, u32, 2> }")
+            .map(drop)
+            .unwrap_err();
+        assert!(message(err).contains("need 3 bytes of padding"));
+    }
+}
@@ -0,0 +1,103 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Type};
+
+/// Derives `piton::Yule` for a struct, checking what implementing it by hand would leave to you.
+///
+/// The struct must be `#[repr(C)]` or `#[repr(transparent)]` and not `packed`, and every field must be
+/// a `Yule` or an array of them. Its size must be the sum of the sizes of its fields, so that it has no padding;
+/// that is checked at compile time, when the type is used if it is generic. `Default` can't be
+/// evaluated at compile time, so `Yule::check` debug asserts that it is all zeros instead.
+#[proc_macro_derive(Yule)]
+pub fn derive_yule(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn derive(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "Yule can only be derived for structs",
+        ));
+    };
+    check_repr(&input)?;
+
+    let name = &input.ident;
+    let field_tys: Vec<&Type> = data.fields.iter().map(|f| &f.ty).collect();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+    for ty in &field_tys {
+        let element = array_element(ty);
+        where_clause
+            .predicates
+            .push(parse_quote!(#element: piton::Yule));
+    }
+
+    let padding_msg = format!("`{}` has padding between or after its fields", name);
+    let default_msg = format!("the `Default` value of `{}` isn't all zeros", name);
+    let eager_assert = input.generics.params.is_empty().then(|| {
+        quote! {
+            const _: () = <#name as piton::Yule>::ASSERT_LAYOUT;
+        }
+    });
+    Ok(quote! {
+        unsafe impl #impl_generics piton::Yule for #name #ty_generics #where_clause {
+            const ASSERT_LAYOUT: () = assert!(
+                core::mem::size_of::<Self>() == 0 #(+ core::mem::size_of::<#field_tys>())*,
+                #padding_msg
+            );
+
+            fn debug_assert_zeroed_default() {
+                debug_assert!(
+                    <Self as piton::Yule>::as_slice(&<Self as Default>::default())
+                        .iter()
+                        .all(|b| *b == 0),
+                    #default_msg
+                );
+            }
+        }
+
+        #eager_assert
+    })
+}
+
+/// Checks that the struct is `#[repr(C)]` or `#[repr(transparent)]`, so that its fields are laid out in order,
+/// and that it isn't `packed`, since its fields could then be misaligned
+fn check_repr(input: &DeriveInput) -> syn::Result<()> {
+    let mut found = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") || meta.path.is_ident("transparent") {
+                found = true;
+            } else if meta.path.is_ident("packed") {
+                return Err(meta.error("Yule can't be derived for `#[repr(packed)]` structs"));
+            } else if meta.input.peek(syn::token::Paren) {
+                // skip the arguments of `align(N)`
+                let _ = meta.input.parse::<proc_macro2::Group>()?;
+            }
+            Ok(())
+        })?;
+    }
+    if found {
+        Ok(())
+    } else {
+        Err(Error::new_spanned(
+            &input.ident,
+            "Yule can only be derived for `#[repr(C)]` or `#[repr(transparent)]` structs",
+        ))
+    }
+}
+
+/// Returns the element type of a (possibly nested) array, or `ty` itself if it isn't one
+fn array_element(ty: &Type) -> &Type {
+    match ty {
+        Type::Array(array) => array_element(&array.elem),
+        Type::Group(group) => array_element(&group.elem),
+        Type::Paren(paren) => array_element(&paren.elem),
+        ty => ty,
+    }
+}
@@ -0,0 +1,6 @@
+//! Checks that `#[derive(Yule)]` rejects the structs it can't vouch for, with errors that say why
+
+#[test]
+fn ui() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
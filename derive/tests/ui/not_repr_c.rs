@@ -0,0 +1,6 @@
+#[derive(piton::Yule)]
+struct Reading {
+    value: piton::types::u32le,
+}
+
+fn main() {}
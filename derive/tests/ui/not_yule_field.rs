@@ -0,0 +1,7 @@
+#[derive(Clone, Default, bytecheck::CheckBytes, piton::Yule)]
+#[repr(C)]
+struct Reading {
+    value: u32,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+#[derive(piton::Yule)]
+#[repr(C, packed)]
+struct Reading {
+    flag: u8,
+    level: u8,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+#[derive(Clone, Default, bytecheck::CheckBytes, piton::Yule)]
+#[repr(C)]
+struct Reading {
+    flag: u8,
+    value: piton::types::u32le,
+}
+
+fn main() {}
@@ -4,5 +4,32 @@ fn main() {
         .client()
         .types()
         .build("./foo.piton")
+        .unwrap();
+    piton_build::RustBuilder::default()
+        .server()
+        .client()
+        .types()
+        .build("./features.piton")
+        .unwrap();
+    piton_build::RustBuilder::default()
+        .types()
+        .build("./network.piton")
+        .unwrap();
+    // The async servers and clients are generated from the same schema, under another name
+    let out = std::path::PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+    std::fs::copy("./features.piton", out.join("features_async.piton")).unwrap();
+    piton_build::RustBuilder::default()
+        .async_()
+        .server()
+        .client()
+        .types()
+        .build(out.join("features_async.piton"))
+        .unwrap();
+    piton_build::RustBuilder::default()
+        .server()
+        .client()
+        .types()
+        .deferred()
+        .build("./deferred.piton")
         .unwrap()
 }
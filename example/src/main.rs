@@ -30,10 +30,12 @@ impl<T: ServiceRx, D: piton::Yule + Default + Clone + std::fmt::Debug + 'static>
 {
     fn xyz<'id>(
         &mut self,
+        ctx: &piton::Context,
         msg: &driver::Bar<D>,
         resp: &mut driver::Test<D>,
+        _tails: piton::Tails<'_>,
     ) -> Result<(), piton::Error> {
-        println!("serv got {:?}", msg);
+        println!("serv got {:?} from {:?}", msg, ctx.caller);
         *resp = driver::Test {
             array: [0xFF; 20],
             ..Default::default()
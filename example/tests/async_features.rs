@@ -0,0 +1,83 @@
+//! Drives the async servers and clients generated from `features.piton` with a minimal executor
+
+use piton::AsyncServiceRx;
+use piton_bbq::Server;
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+#[allow(unused_variables, dead_code, clippy::all)]
+mod features {
+    include!(concat!(env!("OUT_DIR"), "/features_async.rs"));
+}
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark()
+    }
+}
+
+/// Polls `fut` on this thread, parking between polls
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = pin!(fut);
+    loop {
+        if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+            return out;
+        }
+        thread::park();
+    }
+}
+
+struct Pinger;
+
+impl<T: AsyncServiceRx> features::PingerService<T> for Pinger {
+    async fn ping(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &features::Wide,
+        resp: &mut features::Wide,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        resp.x = (u128::from(msg.x) + 1).into();
+        Ok(())
+    }
+}
+
+impl<T: AsyncServiceRx> features::CounterService<T> for Pinger {
+    async fn count(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &features::Wide,
+        resp: &mut features::Wide,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        resp.y = (u64::from(msg.y) + 10).into();
+        Ok(())
+    }
+}
+
+#[test]
+fn async_servers_and_clients_round_trip() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::CounterClient::new(server.client());
+    // Servers run until the process exits, so the thread is left behind
+    thread::spawn(move || block_on(features::CounterServer::new(server, Pinger).run()));
+    block_on(async {
+        for i in 0..4u128 {
+            let mut call = client.ping_ref().await.unwrap();
+            call.x = i.into();
+            assert_eq!(u128::from(call.call().await.unwrap().x), i + 1);
+        }
+        let mut call = client.count_ref().await.unwrap();
+        call.y = 1.into();
+        assert_eq!(u64::from(call.call().await.unwrap().y), 11);
+    });
+}
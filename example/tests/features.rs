@@ -0,0 +1,660 @@
+//! Round trips the features of the IDL in `features.piton` and `deferred.piton` through the generated code and `piton-bbq`
+
+use core::mem::{offset_of, size_of};
+use piton::{PathSegment, ServiceRx, ValidationError, Yule};
+use piton_bbq::Server;
+
+#[allow(unused_variables, dead_code, clippy::all)]
+mod features {
+    include!(concat!(env!("OUT_DIR"), "/features.rs"));
+}
+
+#[allow(dead_code, clippy::all)]
+mod network {
+    include!(concat!(env!("OUT_DIR"), "/network.rs"));
+}
+
+#[allow(unused_variables, dead_code, clippy::all)]
+mod deferred {
+    include!(concat!(env!("OUT_DIR"), "/deferred.rs"));
+}
+
+struct Pinger;
+
+impl<T: ServiceRx> features::PingerService<T> for Pinger {
+    fn ping(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &features::Wide,
+        resp: &mut features::Wide,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        resp.x = (u128::from(msg.x) + 1).into();
+        Ok(())
+    }
+}
+
+impl<T: ServiceRx> features::CounterService<T> for Pinger {
+    fn count(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &features::Wide,
+        resp: &mut features::Wide,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        resp.y = (u64::from(msg.y) + 10).into();
+        Ok(())
+    }
+}
+
+#[test]
+fn derived_services_handle_their_bases_methods() {
+    use features::PingerCalls;
+
+    // Written against the base service, so it works with any service that extends it
+    fn ping(client: &mut impl PingerCalls, x: u128) -> u128 {
+        let mut call = client.ping_ref().unwrap();
+        call.x = x.into();
+        u128::from(call.call().unwrap().x)
+    }
+
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::CounterClient::new(server.client());
+    // Servers run until the process exits, so the thread is left behind
+    std::thread::spawn(move || features::CounterServer::new(server, Pinger).run());
+    assert_eq!(ping(&mut client, 1), 2);
+    let mut call = client.count_ref().unwrap();
+    call.y = 1.into();
+    assert_eq!(u64::from(call.call().unwrap().y), 11);
+}
+
+#[test]
+fn polled_servers_handle_one_request_at_a_time() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::CounterClient::new(server.client());
+    let mut server = features::CounterServer::new(server, Pinger);
+    assert!(matches!(server.poll(), Ok(false)));
+    let client = std::thread::spawn(move || {
+        let mut call = client.ping_ref().unwrap();
+        call.x = 1.into();
+        u128::from(call.call().unwrap().x)
+    });
+    while !server.poll().unwrap() {
+        std::thread::yield_now();
+    }
+    assert_eq!(client.join().unwrap(), 2);
+    // The client was dropped with its thread, so no request can ever arrive
+    assert!(matches!(server.poll(), Err(piton::Error::Closed)));
+}
+
+/// Replies with who sent the request, which of theirs it was, and how many milliseconds it had left
+struct Whoami;
+
+impl<T: ServiceRx> features::PingerService<T> for Whoami {
+    fn ping(
+        &mut self,
+        ctx: &piton::Context,
+        _msg: &features::Wide,
+        resp: &mut features::Wide,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        resp.y = ctx.caller.unwrap().into();
+        resp.z = ctx.request_id.unwrap().into();
+        resp.x = ctx.deadline.map_or(0, |d| d.as_millis()).into();
+        Ok(())
+    }
+}
+
+#[test]
+fn handlers_know_who_called_them() {
+    fn whoami(client: &mut impl features::PingerCalls) -> (u64, u64) {
+        let call = client.ping_ref().unwrap();
+        let resp = call.call().unwrap();
+        (u64::from(resp.y), u64::from(resp.z))
+    }
+
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut first = features::PingerClient::new(server.client());
+    let mut second = features::PingerClient::new(server.client());
+    std::thread::spawn(move || features::PingerServer::new(server, Whoami).run());
+    assert_eq!(whoami(&mut first), (0, 0));
+    assert_eq!(whoami(&mut first), (0, 1));
+    assert_eq!(whoami(&mut second), (1, 0));
+    // Calls without a timeout have no deadline
+    let call = first.ping_ref().unwrap();
+    assert_eq!(u128::from(call.call().unwrap().x), 0);
+    let call = first.ping_ref().unwrap();
+    let resp = call
+        .call_timeout(std::time::Duration::from_secs(10))
+        .unwrap();
+    assert!((1..=10_000).contains(&u128::from(resp.x)));
+}
+
+#[test]
+fn envelope_tags_are_little_endian() {
+    use features::{CounterReq, CounterRet};
+    use piton::Envelope;
+
+    let req = CounterReq::Count(Default::default());
+    assert_eq!(req.as_slice()[..4], [1, 0, 0, 0]);
+    assert_eq!(CounterReq::tag(req.as_slice()), Some(1));
+    let ret = CounterRet::Failed(piton::Status::UnknownMethod);
+    assert_eq!(ret.as_slice()[..8], [2, 0, 0, 0, 2, 0, 0, 0]);
+    assert_eq!(CounterRet::tag(ret.as_slice()), Some(2));
+}
+
+#[test]
+fn newtypes_are_checked_like_what_they_wrap() {
+    use features::{Armed, Meters, Millis, Trip};
+    use PathSegment::{Field, Index};
+
+    let distance: Meters = 12.into();
+    let trip = Trip {
+        duration: Millis::from(piton::types::u64le::from(90)),
+        distance,
+        armed: true.into(),
+        ..Default::default()
+    };
+    assert_eq!(size_of::<Millis>(), size_of::<u64>());
+    assert_eq!(size_of::<Armed>(), size_of::<bool>());
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; size_of::<Trip>()]);
+    let mut buf = Aligned([0; size_of::<Trip>()]);
+    buf.0.copy_from_slice(trip.as_slice());
+    assert_eq!(Trip::check(&buf.0).unwrap(), &trip);
+    buf.0[offset_of!(Trip, armed)] = 2;
+    match Trip::check(&buf.0).map(drop) {
+        Err(ValidationError::Content { path }) => {
+            assert_eq!(path.segments(), [Field("armed"), Index(0)])
+        }
+        res => panic!("expected a content error, got {:?}", res),
+    }
+}
+
+#[test]
+fn strict_flags_reject_unknown_bits() {
+    use features::Faults;
+
+    let mut faults = Faults::OVERHEAT;
+    faults |= Faults::STALL;
+    assert!(faults.contains(Faults::STALL));
+    assert_eq!(faults.bits(), 0b101);
+    assert_eq!(faults.iter().count(), 2);
+    faults.remove(Faults::OVERHEAT);
+    assert_eq!(faults, Faults::STALL);
+    assert_eq!(Faults::from_bits(0b10), None);
+    assert!(Faults::check(&[0b101]).is_ok());
+    assert!(Faults::check(&[0b10]).is_err());
+}
+
+#[test]
+fn integers_use_the_byte_order_they_were_declared_with() {
+    let packet = features::Packet {
+        len: 0x0102_0304.into(),
+        id: 0x0506.into(),
+        ..Default::default()
+    };
+    assert_eq!(packet.as_slice()[..6], [1, 2, 3, 4, 6, 5]);
+    // network.piton is big-endian unless a field says otherwise, and flags follow their integer
+    let header = network::Header {
+        len: 0x0102_0304.into(),
+        checksum: 0x0506.into(),
+        status: network::Status::ERROR,
+        scale: 0.5.into(),
+        ..Default::default()
+    };
+    assert_eq!(
+        header.as_slice()[..12],
+        [1, 2, 3, 4, 6, 5, 2, 0, 0x3f, 0, 0, 0]
+    );
+}
+
+#[test]
+fn floats_use_the_byte_order_they_were_declared_with() {
+    let reading = features::Reading {
+        offset: 1.5.into(),
+        gain: (-2.0).into(),
+        ..Default::default()
+    };
+    assert_eq!(
+        reading.as_slice()[..12],
+        [0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xc0]
+    );
+    assert_eq!(f64::from(reading.offset), 1.5);
+    assert_eq!(f32::from(reading.gain), -2.0);
+}
+
+#[test]
+fn u128_args_are_aligned_after_the_header() {
+    // Each server's queue is allocated afresh, so run a few to try different grant addresses.
+    // Servers run until the process exits, so their threads are left behind.
+    for _ in 0..4 {
+        let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+        let client = server.client();
+        std::thread::spawn(move || features::PingerServer::new(server, Pinger).run());
+        let mut client = features::PingerClient::new(client);
+        for i in 0..8u128 {
+            let mut call = client.ping_ref().unwrap();
+            call.x = (i << 100).into();
+            let resp = call.call().unwrap();
+            assert_eq!(u128::from(resp.x), (i << 100) + 1);
+        }
+    }
+}
+
+struct Opener;
+
+impl<T: ServiceRx> features::OpenerService<T> for Opener {
+    fn open(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &features::Handle,
+        resp: &mut features::Handle,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        resp.id = msg.id.get().unwrap().saturating_add(1).into();
+        resp.parent = msg.id.get().unwrap().get().into();
+        Ok(())
+    }
+}
+
+#[test]
+fn nonzero_fields_round_trip() {
+    use core::num::NonZeroU32;
+
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::OpenerClient::new(server.client());
+    let server = std::thread::spawn(move || features::OpenerServer::new(server, Opener).run());
+    let mut call = client.open_ref().unwrap();
+    // Zero until it's set, which doesn't stop the call from being written
+    assert_eq!(call.id.get(), None);
+    call.id = NonZeroU32::new(7).unwrap().into();
+    let resp = call.call().unwrap();
+    assert_eq!(resp.id.get(), NonZeroU32::new(8));
+    assert_eq!(u32::from(resp.parent), 7);
+    drop(resp);
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+struct Greeter;
+
+impl<T: ServiceRx> features::GreeterService<T> for Greeter {
+    fn greet(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &str,
+        resp: &mut piton::types::String<32>,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        resp.push_str("hello ")
+            .and_then(|_| resp.push_str(msg))
+            .map_err(|_| piton::Error::BufferOverflow)
+    }
+}
+
+#[test]
+fn strings_are_sent_with_their_len() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::GreeterClient::new(server.client());
+    std::thread::spawn(move || features::GreeterServer::new(server, Greeter).run());
+    let mut call = client.greet_ref().unwrap();
+    *call = "piton".try_into().unwrap();
+    assert_eq!(call.as_slice()[..13], *b"\x05\0\0\0\0\0\0\0piton");
+    assert_eq!(call.as_slice()[13..], [0; 11]);
+    assert_eq!(*call.call().unwrap(), "hello piton");
+}
+
+#[test]
+fn maps_and_sets_are_sent_sorted() {
+    use features::Config;
+
+    let mut config = Config::default();
+    config.settings.insert(3.into(), 30.into()).unwrap();
+    config.settings.insert(1.into(), 10.into()).unwrap();
+    config.enabled.insert(7.into()).unwrap();
+    config.enabled.insert(2.into()).unwrap();
+    let bytes = config.as_slice();
+    assert_eq!(
+        bytes[..24],
+        [2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 10, 0, 0, 0, 3, 0, 0, 0, 30, 0, 0, 0]
+    );
+    let enabled = offset_of!(Config, enabled);
+    assert_eq!(
+        bytes[enabled..enabled + 12],
+        [2, 0, 0, 0, 0, 0, 0, 0, 2, 0, 7, 0]
+    );
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; size_of::<Config>()]);
+    let mut buf = Aligned([0; size_of::<Config>()]);
+    buf.0.copy_from_slice(bytes);
+    assert_eq!(Config::check(&buf.0).unwrap(), &config);
+    // Swapping the keys leaves them out of order
+    buf.0[8] = 3;
+    buf.0[16] = 1;
+    assert!(Config::check(&buf.0).is_err());
+}
+
+struct Uploader;
+
+impl<T: ServiceRx> features::UploaderService<T> for Uploader {
+    fn upload(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &features::Upload,
+        resp: &mut features::Upload,
+        mut tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        let name = msg.name.get(tails.req).ok_or(piton::Error::RxFail)?;
+        let chunks = msg.chunks.get(tails.req).ok_or(piton::Error::RxFail)?;
+        resp.name = tails
+            .resp
+            .push_str(&name.to_uppercase())
+            .map_err(|_| piton::Error::BufferOverflow)?;
+        let total: u32 = chunks.iter().map(|c| u32::from(*c)).sum();
+        resp.chunks = tails
+            .resp
+            .push(&[total.into()])
+            .map_err(|_| piton::Error::BufferOverflow)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn tail_fields_round_trip() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default().with_tail_capacity(256);
+    let mut client = features::UploaderClient::new(server.client());
+    std::thread::spawn(move || features::UploaderServer::new(server, Uploader).run());
+    let mut call = client.upload_ref().unwrap();
+    call.with_tail(|upload, mut tail| {
+        upload.name = tail.push_str("piton").unwrap();
+        upload.data = tail.push(&[1u8, 2, 3]).unwrap();
+        upload.chunks = tail.push(&[1u32.into(), 2u32.into()]).unwrap();
+    });
+    let resp = call.call().unwrap();
+    assert_eq!(resp.name.get(resp.tail()), Some("PITON"));
+    assert!(resp.data.is_empty());
+    let chunks = resp.chunks.get(resp.tail()).unwrap();
+    assert_eq!(chunks, [piton::types::u32le::from(3)]);
+}
+
+#[test]
+fn invalid_fields_are_reported_by_path() {
+    use features::{Level, Probe, Rack};
+    use PathSegment::{Field, Index};
+
+    let mut rack = Rack::default();
+    for _ in 0..3 {
+        rack.probes.push(Probe::default()).unwrap();
+    }
+    rack.spare = piton::types::Option::some(Probe::default());
+    rack.levels[1] = Level::High(true);
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; size_of::<Rack>()]);
+    let path_of = |offset: usize, byte: u8| {
+        let mut buf = Aligned([0; size_of::<Rack>()]);
+        buf.0.copy_from_slice(rack.as_slice());
+        buf.0[offset] = byte;
+        match Rack::check(&buf.0).map(drop) {
+            Err(ValidationError::Content { path }) => path.segments().to_vec(),
+            res => panic!("expected a content error, got {:?}", res),
+        }
+    };
+
+    // a vec's elements follow its 8 byte length, and an option's value follows its 8 byte tag
+    let probes = offset_of!(Rack, probes) + 8;
+    assert_eq!(
+        path_of(probes + 2 * size_of::<Probe>(), 2),
+        [Field("probes"), Index(2), Field("enabled")]
+    );
+    assert_eq!(
+        path_of(offset_of!(Rack, spare) + 8, 2),
+        [Field("spare"), Field("value"), Field("enabled")]
+    );
+    let levels = offset_of!(Rack, levels);
+    assert_eq!(
+        path_of(levels + size_of::<Level>() + 1, 2),
+        [Field("levels"), Index(1), Field("High")]
+    );
+    assert_eq!(
+        path_of(levels + 3 * size_of::<Level>(), 7),
+        [Field("levels"), Index(3)]
+    );
+}
+
+#[test]
+fn blocking_run_until_stops_on_shutdown() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::PingerClient::new(server.client());
+    let shutdown = piton::Shutdown::new();
+    let server = {
+        let shutdown = shutdown.clone();
+        std::thread::spawn(move || features::PingerServer::new(server, Pinger).run_until(&shutdown))
+    };
+    let mut call = client.ping_ref().unwrap();
+    call.x = 1.into();
+    assert_eq!(u128::from(call.call().unwrap().x), 2);
+    // The client is still alive, so only the shutdown stops the server
+    shutdown.shutdown();
+    server.join().unwrap().unwrap();
+}
+
+struct Tracer;
+
+impl<T: ServiceRx> features::TracedService<T> for Tracer {
+    fn echo(
+        &mut self,
+        ctx: &piton::Context<features::Trace>,
+        msg: &features::Wide,
+        resp: &mut features::Wide,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        *resp = msg.clone();
+        resp.y = ctx.header.id;
+        Ok(())
+    }
+}
+
+#[test]
+fn headers_reach_the_handler() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::TracedClient::new(server.client());
+    let server = std::thread::spawn(move || features::TracedServer::new(server, Tracer).run());
+    let mut call = client.echo_ref().unwrap();
+    call.header_mut().id = 42.into();
+    // The header survives assigning a new argument
+    *call = features::Wide {
+        z: 7.into(),
+        ..Default::default()
+    };
+    let resp = call.call().unwrap();
+    assert_eq!(u64::from(resp.y), 42);
+    assert_eq!(u64::from(resp.z), 7);
+    drop(resp);
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+/// Sleeps for `y` milliseconds before replying like [`Pinger`]
+struct SlowPinger;
+
+impl<T: ServiceRx> features::PingerService<T> for SlowPinger {
+    fn ping(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &features::Wide,
+        resp: &mut features::Wide,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        std::thread::sleep(std::time::Duration::from_millis(u64::from(msg.y)));
+        resp.x = (u128::from(msg.x) + 1).into();
+        Ok(())
+    }
+}
+
+#[test]
+fn timed_out_calls_dont_get_late_replies() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::PingerClient::new(server.client());
+    let server = std::thread::spawn(move || features::PingerServer::new(server, SlowPinger).run());
+    let mut call = client.ping_ref().unwrap();
+    call.x = 1.into();
+    call.y = 200.into();
+    assert!(matches!(
+        call.call_timeout(std::time::Duration::from_millis(20))
+            .map(drop),
+        Err(piton::Error::Timeout)
+    ));
+    let mut call = client.ping_ref().unwrap();
+    call.x = 10.into();
+    assert_eq!(u128::from(call.call().unwrap().x), 11);
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn server_outlives_clients_it_cant_reply_to() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut gone = features::PingerClient::new(server.client());
+    let mut full = features::PingerClient::new(server.client());
+    let mut client = features::PingerClient::new(server.client());
+    // Sent before the server runs, so that the client is gone by the time it's replied to
+    let _ = gone.ping_ref().unwrap().start().unwrap();
+    drop(gone);
+    let server = std::thread::spawn(move || features::PingerServer::new(server, Pinger).run());
+    // Never collecting the replies fills the client's queue, after which its requests can't be replied to
+    for _ in 0..1024 {
+        if let Ok(call) = full.ping_ref() {
+            let _ = call.start();
+        }
+    }
+    // The queue may still be full of requests the server hasn't got to yet
+    while client.ping_ref().is_err() {
+        std::thread::yield_now();
+    }
+    let mut call = client.ping_ref().unwrap();
+    call.x = 1.into();
+    assert_eq!(u128::from(call.call().unwrap().x), 2);
+    drop((full, client));
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn pipelined_calls_finish_in_any_order() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::PingerClient::new(server.client());
+    let server = std::thread::spawn(move || features::PingerServer::new(server, Pinger).run());
+    let pending: Vec<_> = (0..3u128)
+        .map(|i| {
+            let mut call = client.ping_ref().unwrap();
+            call.x = i.into();
+            call.start().unwrap()
+        })
+        .collect();
+    for (i, call) in pending.into_iter().enumerate().rev() {
+        let resp = call.finish(&mut client.transport).unwrap();
+        assert_eq!(u128::from(resp.x), i as u128 + 1);
+    }
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+struct Thermostat;
+
+impl<T: ServiceRx> features::ThermostatService<T> for Thermostat {
+    fn read(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &features::Wide,
+        resp: &mut features::Wide,
+        _tails: piton::Tails<'_>,
+    ) -> Result<(), piton::Error> {
+        *resp = msg.clone();
+        Ok(())
+    }
+}
+
+#[test]
+fn events_reach_clients_between_calls() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = features::ThermostatClient::new(server.client());
+    let server = features::ThermostatServer::new(server, Thermostat);
+    let mut publisher = server.publisher();
+    let server = std::thread::spawn(move || server.run());
+    assert!(client.try_recv_event().unwrap().is_none());
+    let mut event = publisher.overheated_ref().unwrap();
+    event.x = 90.into();
+    event.send().unwrap();
+    // The event arrives before the reply, and is kept for `recv_event`
+    let mut call = client.read_ref().unwrap();
+    call.x = 1.into();
+    assert_eq!(u128::from(call.call().unwrap().x), 1);
+    let event = client.recv_event().unwrap().unwrap();
+    assert_eq!(event.as_overheated().map(|t| u128::from(t.x)), Some(90));
+    drop(event);
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn bitfield_units_only_hold_valid_members() {
+    use features::{Motor, MotorBitfield0, Speed};
+
+    let mut motor = Motor::default();
+    motor.set_speed(Speed::Fast);
+    motor.set_reverse(true);
+    motor.set_duty(17);
+    assert_eq!(
+        (motor.speed(), motor.reverse(), motor.duty()),
+        (Speed::Fast, true, 17)
+    );
+    let bits = motor._bitfield0.bits();
+    assert_eq!(MotorBitfield0::from_bits(bits), Some(motor._bitfield0));
+    // Speed has three variants, so its two bits can't both be set
+    assert_eq!(MotorBitfield0::from_bits(0b11), None);
+    assert!(Motor::check(&[0b11]).is_err());
+}
+
+struct Halver;
+
+impl<T: piton::DeferRx> deferred::HalverService<T> for Halver
+where
+    T::Ret: deferred::HalverRetVariants,
+{
+    fn half(
+        &mut self,
+        _ctx: &piton::Context,
+        msg: &deferred::Count,
+        _req_tail: &[u8],
+        mut reply: deferred::HalfReply<T>,
+    ) -> Result<(), piton::Error> {
+        let n = u64::from(msg.n);
+        if n % 2 == 1 {
+            // Dropping the reply fails it
+            return Err(piton::Error::Status(piton::Status::HandlerError));
+        }
+        reply.n = (n / 2).into();
+        reply.send()
+    }
+}
+
+#[test]
+fn deferred_handler_errors_dont_stop_the_server() {
+    let mut server = Server::<{ 4096 * 4 }, _, _>::default();
+    let mut client = deferred::HalverClient::new(server.client());
+    let server = std::thread::spawn(move || deferred::HalverServer::new(server, Halver).run());
+    let mut call = client.half_ref().unwrap();
+    call.n = 3.into();
+    assert!(matches!(
+        call.call().map(drop),
+        Err(piton::Error::Status(piton::Status::HandlerError))
+    ));
+    let mut call = client.half_ref().unwrap();
+    call.n = 4.into();
+    assert_eq!(u64::from(call.call().unwrap().n), 2);
+    drop(client);
+    server.join().unwrap().unwrap();
+}
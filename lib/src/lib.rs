@@ -1,27 +1,57 @@
 //#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+// lets `#[derive(Yule)]` refer to `piton::Yule` inside this crate too
+extern crate self as piton;
+
+mod shutdown;
 pub mod types;
+mod validation;
+pub use piton_derive::Yule;
+pub use shutdown::{Shutdown, ShutdownWaker};
+pub use validation::*;
 
-use core::{
-    mem::{align_of, size_of},
-    ops::{Deref, DerefMut},
-};
+use core::{mem::size_of, ops::DerefMut, time::Duration};
 
 /// Yule is Piton's verson of the ULE (unaligned little-endian data) concept from the fantastic [`zerovec`] crate.
 ///
 /// Piton Yules are types that can be safetly zeroed, have no padding, and let you use [`bytecheck`] to verify their contents.
-/// Yules can only hold POD (plain-ole data); i.e they can't hold pointers.
+/// Yules can only hold POD (plain-ole data); i.e they can't hold pointers. Variable-length fields like [`types::Slice`]
+/// instead hold an offset into the trailing region of the message, which is everything in the slice after the Yule itself.
+///
+/// Their [`bytecheck`] errors implement [`CheckError`], so that [`Yule::check`] can report which field was invalid.
+///
+/// Prefer `#[derive(Yule)]` to implementing it by hand, it checks the layout of the struct for you.
 ///
 /// # Safety
 /// By implementing Yule you are guarenteeing that your type has no padding and can safetly be zeroed. If either of those things
 /// are untrue, you are doing a UB.
 pub unsafe trait Yule:
-    bytecheck::CheckBytes<()> + Sized + Default + Clone + 'static
+    bytecheck::CheckBytes<types::TailContext, Error: CheckError> + Sized + Default + Clone + 'static
 {
+    /// Fails to evaluate if the layout of `Self` is wrong. `#[derive(Yule)]` sets this, and
+    /// it is evaluated whenever `Self` is validated or read as bytes.
+    #[doc(hidden)]
+    const ASSERT_LAYOUT: () = ();
+
+    /// Panics in debug builds if `Default` isn't all zeros. `#[derive(Yule)]` sets this, since
+    /// `Default` can't be checked at compile time.
+    #[doc(hidden)]
+    fn debug_assert_zeroed_default() {}
+
+    /// Validates `slice` as `Self`, followed by the trailing region, returning why it isn't valid if it isn't
+    fn check(slice: &[u8]) -> Result<&Self, ValidationError> {
+        let () = Self::ASSERT_LAYOUT;
+        Self::debug_assert_zeroed_default();
+        validation::check_layout::<Self>(slice)?;
+        let mut context = types::TailContext::new(&slice[size_of::<Self>()..]);
+        // Safety: `check_layout` checked that `slice` is long enough and aligned to hold `Self`
+        unsafe { Self::check_bytes(slice.as_ptr() as *const Self, &mut context) }
+            .map_err(|e| ValidationError::Content { path: e.path() })
+    }
+
     fn validate(slice: &[u8]) -> bool {
-        slice.len() >= size_of::<Self>()
-            && slice.as_ptr().align_offset(align_of::<Self>()) == 0
-            && unsafe { Self::check_bytes(slice.as_ptr() as *const Self, &mut ()).is_ok() }
+        Self::check(slice).is_ok()
     }
 
     fn from_mut_slice(slice: &mut [u8]) -> Option<&mut Self> {
@@ -29,7 +59,7 @@ pub unsafe trait Yule:
     }
 
     fn from_slice(slice: &[u8]) -> Option<&Self> {
-        Self::validate(slice).then(|| unsafe { Self::from_slice_unchecked(slice) })
+        Self::check(slice).ok()
     }
 
     ///  Creates a new a mutable reference to `Self` from a slice and does not check validity
@@ -57,23 +87,165 @@ pub unsafe trait Yule:
 
     /// Returns the struct as an byte slice
     fn as_slice(&self) -> &[u8] {
+        let () = Self::ASSERT_LAYOUT;
         // Safety: This is safe due to the bounds on a Yule, essentially no part of the struct shall be uninitialized bytes
         unsafe { core::slice::from_raw_parts(self as *const Self as *const u8, size_of::<Self>()) }
     }
 }
 
-pub trait BufR<'a, T>: Deref<Target = T>
+/// Implemented by the enums generated for service requests, service replies and bus messages.
+///
+/// Each variant of an envelope is laid out as its `u32` tag followed by its payload, so a frame only has
+/// to hold the variant in use, followed by the trailing region. Receivers validate a frame by its tag,
+/// and read it through [`Envelope::Ref`] rather than as the whole envelope. Tags are little-endian
+/// whatever the target, like the rest of the wire format.
+///
+/// # Safety
+/// `frame_len` and `variant_len` must return the size of the tag and payload of the variant, and
+/// `view_unchecked` must not read more than that from the frame.
+pub unsafe trait Envelope: Yule {
+    /// A reference to the payload of whichever variant a frame holds
+    type Ref<'a>;
+
+    /// Returns how many bytes the variant in use takes up, including its tag
+    fn frame_len(&self) -> usize;
+
+    /// Returns how many bytes the variant with `tag` takes up, including its tag
+    fn variant_len(tag: u32) -> Option<usize>;
+
+    /// Validates `frame` as the variant its tag names, followed by the trailing region, returning why
+    /// it isn't valid if it isn't
+    fn check_frame(frame: &[u8]) -> Result<(), ValidationError>;
+
+    /// Creates a reference to the variant in `frame` and does not check validity
+    ///
+    /// # Safety
+    /// `frame` must have passed [`Envelope::check_frame`]
+    unsafe fn view_unchecked(frame: &[u8]) -> Self::Ref<'_>;
+
+    fn validate_frame(frame: &[u8]) -> bool {
+        Self::check_frame(frame).is_ok()
+    }
+
+    fn view(frame: &[u8]) -> Option<Self::Ref<'_>> {
+        Self::validate_frame(frame).then(|| unsafe { Self::view_unchecked(frame) })
+    }
+
+    fn tag(frame: &[u8]) -> Option<u32> {
+        Some(u32::from_le_bytes(frame.get(..4)?.try_into().ok()?))
+    }
+
+    /// Returns where the trailing region starts, in a frame whose variant takes up `frame_len` bytes.
+    ///
+    /// Writers put the trailing region after the whole envelope, then move it down when the frame
+    /// is sent. It is moved by a multiple of 16 bytes, so that its elements stay aligned.
+    fn tail_start(frame_len: usize) -> usize {
+        frame_len + (size_of::<Self>() - frame_len) % 16
+    }
+
+    /// Returns the trailing region of `frame`, that its variable-length fields point into
+    fn tail(frame: &[u8]) -> &[u8] {
+        let start = Self::tag(frame)
+            .and_then(Self::variant_len)
+            .map_or(frame.len(), Self::tail_start);
+        frame.get(start..).unwrap_or(&[])
+    }
+
+    /// Validates the start of `frame` as `V`, the tag and payload of the variant called `name`.
+    ///
+    /// Content errors have `name` at the front of their path.
+    fn check_variant<V>(frame: &[u8], name: &'static str) -> Result<(), ValidationError>
+    where
+        V: bytecheck::CheckBytes<types::TailContext, Error: CheckError>,
+    {
+        validation::check_layout::<V>(frame)?;
+        let mut context = types::TailContext::new(Self::tail(frame));
+        // Safety: `check_layout` checked that `frame` is long enough and aligned to hold `V`
+        unsafe { V::check_bytes(frame.as_ptr() as *const V, &mut context) }
+            .map(drop)
+            .map_err(|e| {
+                ValidationError::Content { path: e.path() }.within(PathSegment::Field(name))
+            })
+    }
+
+    /// Creates a reference to `V`, the tag and payload of one of the variants, from the start of `frame`
+    ///
+    /// # Safety
+    /// `frame` must have passed [`Envelope::check_variant`] for `V`
+    unsafe fn variant_unchecked<V>(frame: &[u8]) -> &V {
+        debug_assert!(frame.len() >= size_of::<V>());
+        &*(frame.as_ptr() as *const V)
+    }
+}
+
+/// Implemented by the envelopes generated for service replies. Besides a variant for each method,
+/// they have one that carries a [`Status`] in place of a return value, for requests the server couldn't handle.
+pub trait Reply: Envelope {
+    /// Returns the status of a reply, which is [`Status::Ok`] unless it is the status variant
+    fn status(view: &Self::Ref<'_>) -> Status;
+
+    /// Turns `self` into the status variant, carrying `status`
+    fn set_status(&mut self, status: Status);
+}
+
+/// Implemented by the request envelopes of services that declare a `header`. Every variant holds
+/// the header ahead of its payload, so it is at the same offset in every frame.
+pub trait Headed: Envelope {
+    type Header: Yule;
+
+    /// Where the header starts in a frame
+    const HEADER_OFFSET: usize;
+
+    fn header(&self) -> &Self::Header;
+
+    fn header_mut(&mut self) -> &mut Self::Header;
+
+    /// Returns the header of `frame`, or `None` if it isn't valid
+    fn frame_header(frame: &[u8]) -> Option<&Self::Header> {
+        Self::Header::from_slice(frame.get(Self::HEADER_OFFSET..)?)
+    }
+}
+
+/// A received frame
+pub trait BufR<'a, T>
 where
-    T: Yule,
+    T: Envelope,
 {
-    fn as_ref(&self) -> &T;
+    /// Returns the frame: the variant in use, followed by the trailing region
+    fn frame(&self) -> &[u8];
+
+    /// Returns a reference to the variant in the frame
+    fn view(&self) -> T::Ref<'_> {
+        T::view(self.frame()).expect("frames are validated when they're received")
+    }
+
+    /// Returns the trailing region of the frame, that its variable-length fields point into
+    fn tail(&self) -> &[u8] {
+        T::tail(self.frame())
+    }
 }
 
-pub trait BufW<'a, T>: BufR<'a, T> + DerefMut<Target = T>
+/// A frame that is being written. It holds room for the whole envelope, but transports only send
+/// the variant in use and the part of the trailing region that was written.
+pub trait BufW<'a, T>: DerefMut<Target = T>
 where
     T: Yule,
 {
     fn as_mut(&mut self) -> &mut T;
+
+    /// Returns the message and a [`types::TailWriter`] for its trailing region, so that
+    /// variable-length fields can be filled in.
+    ///
+    /// Transports that don't send a trailing region can rely on the default, which returns a writer with no capacity.
+    fn split_tail(&mut self) -> (&mut T, types::TailWriter<'_>) {
+        (self.as_mut(), types::TailWriter::empty())
+    }
+
+    /// Calls `f` with the message and a [`types::TailWriter`] for its trailing region
+    fn with_tail<R>(&mut self, f: impl FnOnce(&mut T, types::TailWriter<'_>) -> R) -> R {
+        let (msg, tail) = self.split_tail();
+        f(msg, tail)
+    }
 }
 
 /// `ServiceTx` is implemented by the sender side of a service transport. Service transports
@@ -82,8 +254,8 @@ where
 /// Like all transports, the implementor must provide
 /// buffer types that are owned by the transport.
 pub trait ServiceTx {
-    type Arg: Yule;
-    type Ret: Yule;
+    type Arg: Envelope;
+    type Ret: Reply;
 
     /// A buffer that is issued for writes
     type BufW<'r>: BufW<'r, Self::Arg> + 'r
@@ -95,20 +267,58 @@ pub trait ServiceTx {
     where
         Self: 'r;
 
-    /// Calls the service and waits for a reply.
+    /// Calls the service and waits for a reply. Returns [`Error::Closed`] if the server is gone.
     fn call<'r, 'm>(&'r mut self, msg: Self::BufW<'m>) -> Result<Self::BufR<'r>, Error>;
 
+    /// Calls the service and waits at most `timeout` for a reply, returning [`Error::Timeout`] if none came.
+    ///
+    /// The deadline is sent with the request, so that the server can drop it if it expires before it's handled.
+    /// A reply that turns up after the call has timed out must be discarded by the transport, rather than returned
+    /// from a later call.
+    fn call_timeout<'r, 'm>(
+        &'r mut self,
+        msg: Self::BufW<'m>,
+        timeout: Duration,
+    ) -> Result<Self::BufR<'r>, Error>;
+
+    /// Sends a call without waiting for the reply, so that more calls can be sent before it arrives.
+    /// Collect the reply with [`ServiceTx::finish_call`].
+    fn start_call(&mut self, msg: Self::BufW<'_>) -> Result<PendingCall, Error>;
+
+    /// Waits for the reply to a call sent with [`ServiceTx::start_call`]. Replies can be collected in any order.
+    fn finish_call(&mut self, call: PendingCall) -> Result<Self::BufR<'_>, Error>;
+
     /// Allocs a new writable buffer. Generally these buffers are owned by the transport
     fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error>;
 }
 
+/// A call sent with [`ServiceTx::start_call`] whose reply hasn't been collected yet.
+///
+/// The transport tells calls apart by `id`, which it sends with the request and expects back in the reply.
+/// A transport may hold on to the reply until the call is finished, so don't drop it unfinished.
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[must_use = "the reply is kept until the call is finished"]
+pub struct PendingCall {
+    id: u64,
+}
+
+impl PendingCall {
+    pub fn new(id: u64) -> Self {
+        Self { id }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
 /// `ServiceRx` is implemented by the reciever side of a service transport
 ///
 /// Like all transports, the implementor must provide
 /// buffer types that are owned by the transport.
 pub trait ServiceRx {
-    type Arg: Yule;
-    type Ret: Yule;
+    type Arg: Envelope;
+    type Ret: Reply;
 
     /// A [`Responder`] that allow's a user to respond to a recieved message
     type Responder<'a>: Responder<ServerTransport = Self> + 'a
@@ -125,12 +335,70 @@ pub trait ServiceRx {
     where
         Self: 'r;
 
-    /// Polls the transport for any new messages, returning [`Recv`] containg the responder, a write buffer,
-    /// and the new message
+    /// Waits for a new message, returning [`Recv`] containg the responder, a write buffer,
+    /// and the new message. Returns `None` once the transport is closed.
     #[allow(clippy::type_complexity)]
     fn recv(
         &mut self,
     ) -> Result<Option<Recv<Self::BufW<'_>, Self::BufR<'_>, Self::Responder<'_>>>, Error>;
+
+    /// Checks for a new message without blocking, returning `None` if nothing has arrived yet
+    /// and [`Error::Closed`] once the transport is closed
+    #[allow(clippy::type_complexity)]
+    fn try_recv(
+        &mut self,
+    ) -> Result<Option<Recv<Self::BufW<'_>, Self::BufR<'_>, Self::Responder<'_>>>, Error>;
+
+    /// Waits for a new message like [`ServiceRx::recv`], but also returns `None` once `shutdown` is called.
+    /// Transports wait on a waker registered with [`Shutdown::register`], so that they're woken by the shutdown.
+    #[allow(clippy::type_complexity)]
+    fn recv_until(
+        &mut self,
+        shutdown: &Shutdown,
+    ) -> Result<Option<Recv<Self::BufW<'_>, Self::BufR<'_>, Self::Responder<'_>>>, Error>;
+}
+
+/// Implemented by [`ServiceRx`] transports that can send a reply after they've gone on to receive other requests.
+///
+/// Servers generated with `RustBuilder::deferred()` use it to hand each handler the reply to its request,
+/// which the handler can hold on to and send once it is ready.
+pub trait DeferRx: ServiceRx + 'static {
+    /// Detaches the reply to a received request from the transport, so that it can be sent later
+    #[allow(clippy::type_complexity)]
+    fn defer(
+        resp: Self::BufW<'_>,
+        responder: Self::Responder<'_>,
+    ) -> (Self::BufW<'static>, Self::Responder<'static>);
+
+    /// Sends a deferred reply that was dropped without being sent, which generated servers fail with
+    /// [`Status::HandlerError`] so that the client isn't left waiting. It is sent from `drop`, so it can't block.
+    fn send_dropped(resp: Self::BufW<'static>, responder: Self::Responder<'static>) {
+        let _ = responder.send(resp);
+    }
+}
+
+/// Implemented by [`ServiceRx`] transports whose server can push events to its clients over the same connection.
+///
+/// Events are variants of the reply envelope, so they share its discriminants. Servers generated for services
+/// that declare events use it to make their publisher.
+pub trait EventTx: ServiceRx {
+    /// Sends events to every client of the server. It can be moved to another thread, away from the server.
+    type Publisher: BusTx<Msg = Self::Ret>;
+
+    fn publisher(&self) -> Self::Publisher;
+}
+
+/// Implemented by [`ServiceTx`] transports whose client can receive the events its server pushes.
+///
+/// Events and replies arrive over the same connection, so the transport must keep whichever one
+/// it reads past until it is asked for it.
+pub trait EventRx: ServiceTx {
+    /// Waits for the next event. Returns `None` once the server and its publishers are gone.
+    fn recv_event(&mut self) -> Result<Option<Self::BufR<'_>>, Error>;
+
+    /// Checks for an event without blocking, returning `None` if none has arrived yet
+    /// and [`Error::Closed`] once the server and its publishers are gone
+    fn try_recv_event(&mut self) -> Result<Option<Self::BufR<'_>>, Error>;
 }
 
 /// `BusTx` is implemented by the sender side of a service transport. Bus transports
@@ -139,7 +407,7 @@ pub trait ServiceRx {
 /// Like all transports, the implementor must provide
 /// buffer types that are owned by the transport.
 pub trait BusTx {
-    type Msg: Yule;
+    type Msg: Envelope;
     /// A buffer that is issued for writes
     type BufW<'r>: BufW<'r, Self::Msg> + 'r
     where
@@ -157,20 +425,191 @@ pub trait BusTx {
 /// Like all transports, the implementor must provide
 /// buffer types that are owned by the transport.
 pub trait BusRx {
-    type Msg: Yule;
+    type Msg: Envelope;
     type BufR<'r>: BufR<'r, Self::Msg> + 'r
     where
         Self: 'r;
 
+    /// Waits for a new message. Returns `None` once the transport is closed.
     #[allow(clippy::type_complexity)]
     fn recv(&mut self) -> Result<Option<Self::BufR<'_>>, Error>;
+
+    /// Checks for a new message without blocking, returning `None` if nothing has arrived yet
+    /// and [`Error::Closed`] once the transport is closed
+    fn try_recv(&mut self) -> Result<Option<Self::BufR<'_>>, Error>;
+
+    /// Waits for a new message like [`BusRx::recv`], but also returns `None` once `shutdown` is called.
+    /// Transports wait on a waker registered with [`Shutdown::register`], so that they're woken by the shutdown.
+    fn recv_until(&mut self, shutdown: &Shutdown) -> Result<Option<Self::BufR<'_>>, Error>;
 }
 
-/// A request-reply pair received by [`ServiceRx`]
+/// The async version of [`ServiceTx`], for transports that can wait for a reply without
+/// blocking the thread, like ones running on tokio or embassy
+#[allow(async_fn_in_trait)]
+pub trait AsyncServiceTx {
+    type Arg: Envelope;
+    type Ret: Reply;
+
+    /// A buffer that is issued for writes
+    type BufW<'r>: BufW<'r, Self::Arg> + 'r
+    where
+        Self: 'r;
+
+    /// A buffer that issued for reads, returned by `call`
+    type BufR<'r>: BufR<'r, Self::Ret> + 'r
+    where
+        Self: 'r;
+
+    /// Calls the service and waits for a reply. Returns [`Error::Closed`] if the server is gone.
+    async fn call<'r, 'm>(&'r mut self, msg: Self::BufW<'m>) -> Result<Self::BufR<'r>, Error>;
+
+    /// The async version of [`ServiceTx::call_timeout`]
+    async fn call_timeout<'r, 'm>(
+        &'r mut self,
+        msg: Self::BufW<'m>,
+        timeout: Duration,
+    ) -> Result<Self::BufR<'r>, Error>;
+
+    /// The async version of [`ServiceTx::start_call`]
+    async fn start_call(&mut self, msg: Self::BufW<'_>) -> Result<PendingCall, Error>;
+
+    /// The async version of [`ServiceTx::finish_call`]
+    async fn finish_call(&mut self, call: PendingCall) -> Result<Self::BufR<'_>, Error>;
+
+    /// Allocs a new writable buffer. Generally these buffers are owned by the transport
+    async fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error>
+    where
+        Self: 'r;
+}
+
+/// The async version of [`ServiceRx`]
+#[allow(async_fn_in_trait)]
+pub trait AsyncServiceRx {
+    type Arg: Envelope;
+    type Ret: Reply;
+
+    /// An [`AsyncResponder`] that allow's a user to respond to a recieved message
+    type Responder<'a>: AsyncResponder<ServerTransport = Self> + 'a
+    where
+        Self: 'a;
+
+    /// A buffer that issued for reads, returned by `call`
+    type BufR<'r>: BufR<'r, Self::Arg> + 'r
+    where
+        Self: 'r;
+
+    /// A buffer that is issued for writes
+    type BufW<'r>: BufW<'r, Self::Ret> + 'r
+    where
+        Self: 'r;
+
+    /// Waits for a new message, returning [`Recv`] containg the responder, a write buffer,
+    /// and the new message
+    #[allow(clippy::type_complexity)]
+    async fn recv(
+        &mut self,
+    ) -> Result<Option<Recv<Self::BufW<'_>, Self::BufR<'_>, Self::Responder<'_>>>, Error>;
+}
+
+/// The async version of [`DeferRx`]
+pub trait AsyncDeferRx: AsyncServiceRx + 'static {
+    /// Detaches the reply to a received request from the transport, so that it can be sent later
+    #[allow(clippy::type_complexity)]
+    fn defer(
+        resp: Self::BufW<'_>,
+        responder: Self::Responder<'_>,
+    ) -> (Self::BufW<'static>, Self::Responder<'static>);
+
+    /// Sends a deferred reply that was dropped without being sent, like [`DeferRx::send_dropped`]. It is sent
+    /// from `drop`, so it can't be awaited.
+    fn send_dropped(resp: Self::BufW<'static>, responder: Self::Responder<'static>);
+}
+
+/// The async version of [`EventTx`]
+pub trait AsyncEventTx: AsyncServiceRx {
+    type Publisher: AsyncBusTx<Msg = Self::Ret>;
+
+    fn publisher(&self) -> Self::Publisher;
+}
+
+/// The async version of [`EventRx`]
+#[allow(async_fn_in_trait)]
+pub trait AsyncEventRx: AsyncServiceTx {
+    async fn recv_event(&mut self) -> Result<Option<Self::BufR<'_>>, Error>;
+}
+
+/// The async version of [`BusTx`]
+#[allow(async_fn_in_trait)]
+pub trait AsyncBusTx {
+    type Msg: Envelope;
+    /// A buffer that is issued for writes
+    type BufW<'r>: BufW<'r, Self::Msg> + 'r
+    where
+        Self: 'r;
+
+    /// Sends a message onto the bus transport
+    async fn send(&'_ mut self, msg: Self::BufW<'_>) -> Result<(), Error>;
+
+    /// Allocs a new writable buffer. Generally these buffers are owned by the transport
+    async fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error>
+    where
+        Self: 'r;
+}
+
+/// The async version of [`BusRx`]
+#[allow(async_fn_in_trait)]
+pub trait AsyncBusRx {
+    type Msg: Envelope;
+    type BufR<'r>: BufR<'r, Self::Msg> + 'r
+    where
+        Self: 'r;
+
+    async fn recv(&mut self) -> Result<Option<Self::BufR<'_>>, Error>;
+}
+
+/// A request-reply pair received by [`ServiceRx`] or [`AsyncServiceRx`]
 pub struct Recv<BW, BR, R> {
     pub req: BR,
     pub resp: BW,
     pub responder: R,
+    /// What the transport knows about the request
+    pub ctx: Context,
+}
+
+/// What the transport knows about a request, passed to generated service handlers.
+///
+/// Services that declare a `header` get it here too, as `H`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Context<H = ()> {
+    /// Identifies the client that sent the request, if the transport tells clients apart
+    pub caller: Option<u64>,
+    /// Identifies the request among the caller's requests, if the transport numbers them
+    pub request_id: Option<u64>,
+    /// How long the caller had left to wait for the reply when the request was received, if it set a
+    /// deadline and the transport passes it on. Handlers can give up on requests that won't make it.
+    pub deadline: Option<core::time::Duration>,
+    /// The header sent with the request, or `()` if the service doesn't declare one
+    pub header: H,
+}
+
+impl Context {
+    /// Returns a context with the same transport info, carrying `header`
+    pub fn with_header<H>(self, header: H) -> Context<H> {
+        Context {
+            caller: self.caller,
+            request_id: self.request_id,
+            deadline: self.deadline,
+            header,
+        }
+    }
+}
+
+/// The trailing regions of a request and its response, passed to generated service handlers
+pub struct Tails<'a> {
+    /// The trailing region of the request, that its variable-length fields point into
+    pub req: &'a [u8],
+    /// Writes the elements of the response's variable-length fields
+    pub resp: types::TailWriter<'a>,
 }
 
 /// `Responder` is implemented by structs that allow a user to respond to a request.
@@ -182,13 +621,33 @@ pub trait Responder {
     fn send(self, msg: <Self::ServerTransport as ServiceRx>::BufW<'_>) -> Result<(), Error>;
 }
 
+/// The async version of [`Responder`]
+#[allow(async_fn_in_trait)]
+pub trait AsyncResponder {
+    /// The [`AsyncServiceRx`] that this responder is associated with
+    type ServerTransport: AsyncServiceRx;
+
+    /// Sends a response
+    async fn send(
+        self,
+        msg: <Self::ServerTransport as AsyncServiceRx>::BufW<'_>,
+    ) -> Result<(), Error>;
+}
+
 #[derive(Debug)]
 pub enum Error {
     BufferUnderflow,
     BufferOverflow,
-    InvalidMsg,
+    /// A received frame wasn't a valid message
+    InvalidMsg(ValidationError),
     TxFail,
     RxFail,
+    /// The transport is closed, because the other end has been dropped
+    Closed,
+    /// The server couldn't handle the request, and replied with this status
+    Status(Status),
+    /// No reply came before the call's deadline
+    Timeout,
 }
 
 impl core::fmt::Display for Error {
@@ -196,9 +655,12 @@ impl core::fmt::Display for Error {
         match self {
             Error::BufferUnderflow => write!(f, "buffer underflow"),
             Error::BufferOverflow => write!(f, "buffer overflow"),
-            Error::InvalidMsg => write!(f, "invalid msg"),
+            Error::InvalidMsg(e) => write!(f, "invalid msg: {}", e),
             Error::TxFail => write!(f, "tx fail"),
             Error::RxFail => write!(f, "rx fail"),
+            Error::Closed => write!(f, "closed"),
+            Error::Status(s) => write!(f, "server replied with {}", s),
+            Error::Timeout => write!(f, "timed out"),
         }
     }
 }
@@ -206,7 +668,55 @@ impl core::fmt::Display for Error {
 #[cfg(feature = "std")]
 impl ::std::error::Error for Error {}
 
-#[derive(Clone, bytecheck::CheckBytes, PartialEq, Eq, Hash)]
+/// Whether the server handled a request, sent back in the [`Reply`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, bytecheck::CheckBytes)]
+#[repr(u32)]
+pub enum Status {
+    #[default]
+    Ok = 0u32.to_le(),
+    /// The handler returned an error
+    HandlerError = 1u32.to_le(),
+    /// The server doesn't have the method that was called
+    UnknownMethod = 2u32.to_le(),
+    /// The request wasn't a valid message
+    InvalidMsg = 3u32.to_le(),
+}
+
+unsafe impl Yule for Status {}
+
+impl Status {
+    /// Returns [`Error::Status`] unless `self` is [`Status::Ok`]
+    pub fn into_result(self) -> Result<(), Error> {
+        match self {
+            Status::Ok => Ok(()),
+            status => Err(Error::Status(status)),
+        }
+    }
+}
+
+impl From<ValidationError> for Status {
+    /// Requests with an unknown tag are for a method the server doesn't have, anything else is invalid
+    fn from(e: ValidationError) -> Self {
+        match e {
+            ValidationError::UnknownTag(_) => Status::UnknownMethod,
+            _ => Status::InvalidMsg,
+        }
+    }
+}
+
+impl core::fmt::Display for Status {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Status::Ok => write!(f, "ok"),
+            Status::HandlerError => write!(f, "handler error"),
+            Status::UnknownMethod => write!(f, "unknown method"),
+            Status::InvalidMsg => write!(f, "invalid msg"),
+        }
+    }
+}
+
+#[derive(Clone, bytecheck::CheckBytes, Yule, PartialEq, Eq, Hash)]
+#[repr(transparent)]
 pub struct ZeroPad<const N: usize> {
     _pad: [u8; N],
 }
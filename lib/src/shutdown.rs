@@ -0,0 +1,191 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    future::{poll_fn, Future},
+    pin::pin,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    task::{Poll, Waker},
+};
+use std::sync::Mutex;
+
+/// Asks servers to stop. Pass it to a generated server's `run_until`, and call
+/// [`Shutdown::shutdown`] on a clone of it to make `run_until` return.
+///
+/// Clones share the same state, so one token can stop any number of servers.
+#[derive(Clone, Default)]
+pub struct Shutdown(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    requested: AtomicBool,
+    /// The wakers registered with [`Shutdown::register`], by the id of their [`ShutdownWaker`]
+    wakers: Mutex<Vec<(usize, Waker)>>,
+    next_id: AtomicUsize,
+}
+
+impl Inner {
+    fn with_wakers<R>(&self, f: impl FnOnce(&mut Vec<(usize, Waker)>) -> R) -> R {
+        // The wakers are left consistent by every change to them, so they're still usable after a panic
+        let mut wakers = self.wakers.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut wakers)
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes every server running until this token return once it has finished the request it is handling
+    pub fn shutdown(&self) {
+        self.0.requested.store(true, Ordering::Release);
+        for (_, waker) in self.0.with_wakers(core::mem::take) {
+            waker.wake();
+        }
+    }
+
+    /// Registers `waker` to be woken when [`Shutdown::shutdown`] is called, until the returned
+    /// [`ShutdownWaker`] is dropped. Blocking transports use it to stop waiting once a server is asked to stop.
+    pub fn register(&self, waker: &Waker) -> ShutdownWaker<'_> {
+        let id = self.0.next_id.fetch_add(1, Ordering::Relaxed);
+        self.0
+            .with_wakers(|wakers| wakers.push((id, waker.clone())));
+        ShutdownWaker { inner: &self.0, id }
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.0.requested.load(Ordering::Acquire)
+    }
+
+    /// Runs `fut` until it completes, or returns `None` once [`Shutdown::shutdown`] is called
+    pub async fn until<F: Future>(&self, fut: F) -> Option<F::Output> {
+        let mut fut = pin!(fut);
+        let mut registered: Option<ShutdownWaker<'_>> = None;
+        poll_fn(|cx| {
+            if self.is_shutdown() {
+                return Poll::Ready(None);
+            }
+            if let Poll::Ready(out) = fut.as_mut().poll(cx) {
+                return Poll::Ready(Some(out));
+            }
+            match &registered {
+                Some(registered) => registered.update(cx.waker()),
+                None => registered = Some(self.register(cx.waker())),
+            }
+            // `shutdown` may have been called before the waker was registered
+            if self.is_shutdown() {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// A waker registered with [`Shutdown::register`], which is unregistered when this is dropped
+pub struct ShutdownWaker<'a> {
+    inner: &'a Inner,
+    id: usize,
+}
+
+impl ShutdownWaker<'_> {
+    /// Replaces the registered waker with `waker`, for a task that has moved
+    pub fn update(&self, waker: &Waker) {
+        self.inner.with_wakers(
+            |wakers| match wakers.iter_mut().find(|(id, _)| *id == self.id) {
+                Some((_, w)) if w.will_wake(waker) => {}
+                Some((_, w)) => *w = waker.clone(),
+                None => wakers.push((self.id, waker.clone())),
+            },
+        );
+    }
+}
+
+impl Drop for ShutdownWaker<'_> {
+    fn drop(&mut self) {
+        self.inner
+            .with_wakers(|wakers| wakers.retain(|(id, _)| *id != self.id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::task::Context;
+    use core::time::Duration;
+    use std::{
+        task::Wake,
+        thread::{self, Thread},
+    };
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Polls `fut` on this thread, parking between polls, and returns its output and how many polls it took
+    fn block_on<F: Future>(fut: F) -> (F::Output, usize) {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = pin!(fut);
+        let mut polls = 0;
+        loop {
+            polls += 1;
+            if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+                return (out, polls);
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn until_is_woken_by_shutdown() {
+        let shutdown = Shutdown::new();
+        let stopper = {
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                shutdown.shutdown();
+            })
+        };
+        let (out, polls) = block_on(shutdown.until(core::future::pending::<()>()));
+        stopper.join().unwrap();
+        assert_eq!(out, None);
+        assert!(polls <= 3, "polled {polls} times");
+        assert!(shutdown.0.with_wakers(|wakers| wakers.is_empty()));
+    }
+
+    #[test]
+    fn until_unregisters_its_waker() {
+        let shutdown = Shutdown::new();
+        let mut yielded = false;
+        let yield_once = poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        });
+        assert_eq!(block_on(shutdown.until(yield_once)), (Some(()), 2));
+        assert!(shutdown.0.with_wakers(|wakers| wakers.is_empty()));
+
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut until = Box::pin(shutdown.until(core::future::pending::<()>()));
+        let _ = until.as_mut().poll(&mut Context::from_waker(&waker));
+        assert_eq!(shutdown.0.with_wakers(|wakers| wakers.len()), 1);
+        drop(until);
+        assert!(shutdown.0.with_wakers(|wakers| wakers.is_empty()));
+    }
+
+    #[test]
+    fn until_returns_the_output_before_shutdown() {
+        let shutdown = Shutdown::new();
+        assert_eq!(block_on(shutdown.until(async { 7 })).0, Some(7));
+        shutdown.shutdown();
+        assert_eq!(block_on(shutdown.until(async { 7 })).0, None);
+    }
+}
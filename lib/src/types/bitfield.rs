@@ -0,0 +1,26 @@
+use core::fmt;
+
+/// Returned by the [`bytecheck::CheckBytes`] impl of a packed bitfield when a member
+/// holds a value that isn't valid for its type, such as an unknown enum discriminant
+#[derive(Debug)]
+pub struct InvalidBitfieldError {
+    /// The name of the invalid member
+    pub field_name: &'static str,
+}
+
+impl fmt::Display for InvalidBitfieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid value for bitfield member {}", self.field_name)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for InvalidBitfieldError {}
+
+impl crate::CheckError for InvalidBitfieldError {
+    fn path(&self) -> crate::FieldPath {
+        let mut path = crate::FieldPath::new();
+        path.push_front(crate::PathSegment::Field(self.field_name));
+        path
+    }
+}
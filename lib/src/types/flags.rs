@@ -0,0 +1,24 @@
+use core::fmt;
+
+/// Returned by the [`bytecheck::CheckBytes`] impl of a `strict` flags type
+/// when bits are set that don't belong to any flag
+#[derive(Debug)]
+pub struct UnknownFlagsError {
+    /// The bits that didn't belong to any flag
+    pub bits: u64,
+}
+
+impl fmt::Display for UnknownFlagsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown flag bits {:#x}", self.bits)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for UnknownFlagsError {}
+
+impl crate::CheckError for UnknownFlagsError {
+    fn path(&self) -> crate::FieldPath {
+        crate::FieldPath::new()
+    }
+}
@@ -1,6 +1,6 @@
 // based on https://docs.rs/nora_endian/0.1.2/src/nora_endian/lib.rs.html
 // rehomed here so we can implement bytecheck and force consistent alignment
-use core::{fmt, num::*, ops::*};
+use core::{cmp::Ordering, fmt, num::*, ops::*};
 
 macro_rules! ety {
     ($name:ident, $ty:ty, $trait:ident.$fn:ident, $traitas:ident.$fnas:ident) => {
@@ -48,10 +48,23 @@ macro_rules! ety {
     };
     (tyonly $ty:ty, $name:ident, $to:ident, $align:literal) => {
         #[allow(non_camel_case_types)]
-        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
         #[repr(C, align($align))]
         pub struct $name($ty);
 
+        // ordered by value rather than by the stored bytes, so sorted data is sorted the same on every host
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                <$ty>::from(*self).cmp(&<$ty>::from(*other))
+            }
+        }
+
         impl PartialEq<$ty> for $name {
             fn eq(&self, rhs: &$ty) -> bool {
                 <$ty>::from(*self).eq(rhs)
@@ -66,25 +79,25 @@ macro_rules! ety {
 
         impl fmt::Debug for $name {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                <$ty>::from(self.0).fmt(f)
+                <$ty>::from(*self).fmt(f)
             }
         }
 
         impl fmt::Display for $name {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                <$ty>::from(self.0).fmt(f)
+                <$ty>::from(*self).fmt(f)
             }
         }
 
         impl fmt::LowerHex for $name {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                <$ty>::from(self.0).fmt(f)
+                <$ty>::from(*self).fmt(f)
             }
         }
 
         impl fmt::UpperHex for $name {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                <$ty>::from(self.0).fmt(f)
+                <$ty>::from(*self).fmt(f)
             }
         }
     };
@@ -173,6 +186,184 @@ macro_rules! ety {
             }
         }
     };
+    (float $ty:ty, $bits:ty, $name:ident, $to:ident, $align:literal) => {
+        /// A float stored in a fixed byte order.
+        ///
+        /// Equality and ordering follow IEEE 754's total order (see [`f32::total_cmp`]),
+        /// so `NaN == NaN` and `-0.0 != 0.0`. Use [`Self::get`] to compare native floats.
+        #[allow(non_camel_case_types)]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        #[repr(C, align($align))]
+        pub struct $name($bits);
+
+        impl $name {
+            pub fn new(value: $ty) -> Self {
+                Self(value.to_bits().$to())
+            }
+
+            pub fn get(self) -> $ty {
+                <$ty>::from_bits(self.0.$to())
+            }
+
+            pub fn total_cmp(&self, other: &Self) -> Ordering {
+                self.get().total_cmp(&other.get())
+            }
+
+            pub fn is_nan(self) -> bool {
+                self.get().is_nan()
+            }
+
+            pub fn is_finite(self) -> bool {
+                self.get().is_finite()
+            }
+
+            pub fn is_infinite(self) -> bool {
+                self.get().is_infinite()
+            }
+        }
+
+        impl From<$ty> for $name {
+            fn from(value: $ty) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for $ty {
+            fn from(value: $name) -> Self {
+                value.get()
+            }
+        }
+
+        impl Default for $name {
+            #[inline(always)]
+            fn default() -> Self {
+                Self(0)
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.total_cmp(other)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.get().fmt(f)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.get().fmt(f)
+            }
+        }
+
+        ety!($name, $ty, Add.add, AddAssign.add_assign);
+        ety!($name, $ty, Sub.sub, SubAssign.sub_assign);
+        ety!($name, $ty, Mul.mul, MulAssign.mul_assign);
+        ety!($name, $ty, Div.div, DivAssign.div_assign);
+        ety!($name, $ty, Rem.rem, RemAssign.rem_assign);
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self::new(-self.get())
+            }
+        }
+
+        impl<C: ?Sized> bytecheck::CheckBytes<C> for $name {
+            type Error = core::convert::Infallible;
+
+            #[inline]
+            unsafe fn check_bytes<'a>(
+                value: *const Self,
+                _: &mut C,
+            ) -> Result<&'a Self, Self::Error> {
+                Ok(&*value)
+            }
+        }
+    };
+    (char $name:ident, $to:ident) => {
+        /// A `char` stored in a fixed byte order, checked to be a valid Unicode scalar value by bytecheck
+        #[allow(non_camel_case_types)]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+        #[repr(C, align(4))]
+        pub struct $name(u32);
+
+        impl $name {
+            pub const fn new(value: char) -> Self {
+                Self((value as u32).$to())
+            }
+
+            pub fn get(self) -> char {
+                char::from_u32(self.0.$to()).unwrap_or(char::REPLACEMENT_CHARACTER)
+            }
+        }
+
+        impl From<char> for $name {
+            fn from(value: char) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for char {
+            fn from(value: $name) -> Self {
+                value.get()
+            }
+        }
+
+        impl PartialEq<char> for $name {
+            fn eq(&self, rhs: &char) -> bool {
+                self.get().eq(rhs)
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.get().cmp(&other.get())
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.get().fmt(f)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.get().fmt(f)
+            }
+        }
+
+        impl<C: ?Sized> bytecheck::CheckBytes<C> for $name {
+            type Error = bytecheck::CharCheckError;
+
+            #[inline]
+            unsafe fn check_bytes<'a>(
+                value: *const Self,
+                _: &mut C,
+            ) -> Result<&'a Self, Self::Error> {
+                let invalid_value = (*value).0.$to();
+                char::from_u32(invalid_value).ok_or(bytecheck::CharCheckError { invalid_value })?;
+                Ok(&*value)
+            }
+        }
+    };
     (be $ty:ty, $name:ident, $align:literal) => {
         ety!($ty, $name, to_be, $align);
     };
@@ -190,27 +381,43 @@ macro_rules! ety {
 ety!(be u16, u16be, 2);
 ety!(be u32, u32be, 4);
 ety!(be u64, u64be, 8);
+ety!(be u128, u128be, 16);
 ety!(le u16, u16le, 2);
 ety!(le u32, u32le, 4);
 ety!(le u64, u64le, 8);
+ety!(le u128, u128le, 16);
 
 ety!(be i16, i16be, 2);
 ety!(be i32, i32be, 4);
 ety!(be i64, i64be, 8);
+ety!(be i128, i128be, 16);
 ety!(le i16, i16le, 2);
 ety!(le i32, i32le, 4);
 ety!(le i64, i64le, 8);
+ety!(le i128, i128le, 16);
+
+ety!(char charbe, to_be);
+ety!(char charle, to_le);
+
+ety!(float f32, u32, f32be, to_be, 4);
+ety!(float f64, u64, f64be, to_be, 8);
+ety!(float f32, u32, f32le, to_le, 4);
+ety!(float f64, u64, f64le, to_le, 8);
 
 ety!(nz be u16be, NonZeroU16, NonZeroU16be, 2);
 ety!(nz be u32be, NonZeroU32, NonZeroU32be, 4);
 ety!(nz be u64be, NonZeroU64, NonZeroU64be, 8);
+ety!(nz be u128be, NonZeroU128, NonZeroU128be, 16);
 ety!(nz le u16le, NonZeroU16, NonZeroU16le, 2);
 ety!(nz le u32le, NonZeroU32, NonZeroU32le, 4);
 ety!(nz le u64le, NonZeroU64, NonZeroU64le, 8);
+ety!(nz le u128le, NonZeroU128, NonZeroU128le, 16);
 
 ety!(nz be i16be, NonZeroI16, NonZeroI16be, 2);
 ety!(nz be i32be, NonZeroI32, NonZeroI32be, 4);
 ety!(nz be i64be, NonZeroI64, NonZeroI64be, 8);
+ety!(nz be i128be, NonZeroI128, NonZeroI128be, 16);
 ety!(nz le i16le, NonZeroI16, NonZeroI16le, 2);
 ety!(nz le i32le, NonZeroI32, NonZeroI32le, 4);
 ety!(nz le i64le, NonZeroI64, NonZeroI64le, 8);
+ety!(nz le i128le, NonZeroI128, NonZeroI128le, 16);
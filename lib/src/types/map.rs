@@ -0,0 +1,234 @@
+use super::Vec;
+use core::{fmt, mem::size_of, ptr::addr_of};
+
+use bytecheck::CheckBytes;
+
+use crate::{CheckError, PathCheckError, PathSegment};
+
+/// A key and its value, as stored in a [`Map`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
+#[repr(C)]
+pub struct Entry<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+impl<C, K: CheckBytes<C>, V: CheckBytes<C>> CheckBytes<C> for Entry<K, V>
+where
+    K::Error: CheckError,
+    V::Error: CheckError,
+{
+    type Error = PathCheckError;
+    unsafe fn check_bytes<'__bytecheck>(
+        value: *const Self,
+        context: &mut C,
+    ) -> ::core::result::Result<&'__bytecheck Self, PathCheckError> {
+        K::check_bytes(addr_of!((*value).key), context)
+            .map_err(|e| PathCheckError::within(PathSegment::Field("key"), &e))?;
+        V::check_bytes(addr_of!((*value).value), context)
+            .map_err(|e| PathCheckError::within(PathSegment::Field("value"), &e))?;
+        Ok(&*value)
+    }
+}
+
+unsafe impl<K: crate::Yule, V: crate::Yule> crate::Yule for Entry<K, V> {
+    const ASSERT_LAYOUT: () = assert!(
+        size_of::<Self>() == size_of::<K>() + size_of::<V>(),
+        "map entries can't have padding between their key and value"
+    );
+}
+
+/// A fixed-capacity map, that keeps its entries sorted by key so lookups can binary search.
+///
+/// Validation checks that the keys are sorted and unique.
+#[repr(C)]
+pub struct Map<K, V, const N: usize> {
+    entries: Vec<Entry<K, V>, N>,
+}
+
+impl<K, V, const N: usize> Map<K, V, N> {
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entries, sorted by key
+    pub fn as_slice(&self) -> &[Entry<K, V>] {
+        self.entries.as_slice()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|e| (&e.key, &e.value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|e| &e.key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|e| &e.value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+}
+
+impl<K: Ord, V, const N: usize> Map<K, V, N> {
+    fn find(&self, key: &K) -> Result<usize, usize> {
+        self.entries.binary_search_by(|e| e.key.cmp(key))
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let index = self.find(key).ok()?;
+        Some(&self.entries[index].value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let index = self.find(key).ok()?;
+        Some(&mut self.entries[index].value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_ok()
+    }
+
+    /// Inserts `value` under `key`, returning the value it replaced if there was one.
+    /// Returns the key and value back if the map is full.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        match self.find(&key) {
+            Ok(index) => Ok(Some(core::mem::replace(
+                &mut self.entries[index].value,
+                value,
+            ))),
+            Err(index) => {
+                self.entries
+                    .insert(index, Entry { key, value })
+                    .map_err(|e| (e.key, e.value))?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let index = self.find(key).ok()?;
+        Some(self.entries.remove(index).value)
+    }
+}
+
+impl<K, V, const N: usize> Default for Map<K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Clone, V: Clone, const N: usize> Clone for Map<K, V, N> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, const N: usize> fmt::Debug for Map<K, V, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: PartialEq, V: PartialEq, const N: usize> PartialEq for Map<K, V, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl<K: Eq, V: Eq, const N: usize> Eq for Map<K, V, N> {}
+
+/// Paths lead to the invalid entry by index. An entry whose key isn't greater than the previous one's is invalid at its key.
+impl<C, K: CheckBytes<C> + Ord, V: CheckBytes<C>, const N: usize> CheckBytes<C> for Map<K, V, N>
+where
+    K::Error: CheckError,
+    V::Error: CheckError,
+{
+    type Error = PathCheckError;
+    unsafe fn check_bytes<'__bytecheck>(
+        value: *const Self,
+        context: &mut C,
+    ) -> ::core::result::Result<&'__bytecheck Self, PathCheckError> {
+        let entries = Vec::<Entry<K, V>, N>::check_bytes(addr_of!((*value).entries), context)?;
+        if let Some(index) = entries.windows(2).position(|w| w[0].key >= w[1].key) {
+            let key = PathCheckError::at(PathSegment::Field("key"));
+            return Err(PathCheckError::within(PathSegment::Index(index + 1), &key));
+        }
+        Ok(&*value)
+    }
+}
+
+unsafe impl<K: crate::Yule + Ord, V: crate::Yule, const N: usize> crate::Yule for Map<K, V, N> {
+    const ASSERT_LAYOUT: () = {
+        let () = <Entry<K, V> as crate::Yule>::ASSERT_LAYOUT;
+        <Vec<Entry<K, V>, N> as crate::Yule>::ASSERT_LAYOUT
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FieldPath, ValidationError, Yule};
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; 24]);
+
+    fn path_of(buf: &[u8]) -> FieldPath {
+        match Map::<u8, bool, 8>::check(buf).map(drop) {
+            Err(ValidationError::Content { path }) => path,
+            res => panic!("expected a content error, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn keeps_entries_sorted_by_key() {
+        let mut map = Map::<u8, u8, 2>::new();
+        assert_eq!(map.insert(3, 30), Ok(None));
+        assert_eq!(map.insert(1, 10), Ok(None));
+        assert_eq!(map.insert(3, 31), Ok(Some(30)));
+        assert_eq!(map.insert(2, 20), Err((2, 20)));
+        assert_eq!(map.keys().copied().collect::<std::vec::Vec<_>>(), [1, 3]);
+        assert_eq!(map.get(&3), Some(&31));
+        assert_eq!(map.remove(&1), Some(10));
+        assert!(!map.contains_key(&1));
+    }
+
+    #[test]
+    fn checks_keys_are_sorted_and_unique() {
+        let mut buf = Aligned([0; 24]);
+        buf.0[0] = 2;
+        buf.0[8..12].copy_from_slice(&[1, 1, 2, 0]);
+        let map = Map::<u8, bool, 8>::check(&buf.0).unwrap();
+        assert_eq!(map.get(&2), Some(&false));
+        buf.0[11] = 2;
+        assert_eq!(
+            path_of(&buf.0).segments(),
+            &[PathSegment::Index(1), PathSegment::Field("value")]
+        );
+        buf.0[11] = 0;
+        buf.0[10] = 1;
+        assert_eq!(
+            path_of(&buf.0).segments(),
+            &[PathSegment::Index(1), PathSegment::Field("key")]
+        );
+    }
+}
@@ -1,4 +1,20 @@
+mod bitfield;
+mod flags;
+mod map;
+mod nonzero;
+mod option;
+mod set;
+mod string;
+mod tail;
 mod vec;
+pub use bitfield::*;
+pub use flags::*;
+pub use map::*;
+pub use nonzero::*;
+pub use option::*;
+pub use set::*;
+pub use string::*;
+pub use tail::*;
 pub use vec::*;
 pub mod le;
 pub use le::*;
@@ -17,6 +33,22 @@ primative_yule_impl! { i64le }
 primative_yule_impl! { i32le }
 primative_yule_impl! { i16le }
 primative_yule_impl! { i8 }
+primative_yule_impl! { u64be }
+primative_yule_impl! { u32be }
+primative_yule_impl! { u16be }
+primative_yule_impl! { i64be }
+primative_yule_impl! { i32be }
+primative_yule_impl! { i16be }
+primative_yule_impl! { u128le }
+primative_yule_impl! { i128le }
+primative_yule_impl! { u128be }
+primative_yule_impl! { i128be }
+primative_yule_impl! { charle }
+primative_yule_impl! { charbe }
+primative_yule_impl! { f64le }
+primative_yule_impl! { f32le }
+primative_yule_impl! { f64be }
+primative_yule_impl! { f32be }
 primative_yule_impl! { f64 }
 primative_yule_impl! { f32 }
 primative_yule_impl! { bool }
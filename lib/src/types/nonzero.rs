@@ -0,0 +1,175 @@
+use super::le::*;
+use bytecheck::NonZeroCheckError;
+use core::{mem::size_of, num::*};
+
+/// A `nonzero` integer, which fails validation if it is zero.
+///
+/// The `NonZero*` types can't be zeroed, so it is stored as the plain integer, which lets it be used in a
+/// [`Yule`](crate::Yule). It defaults to zero like every other Yule, so it has to be set before it is sent.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+#[repr(transparent)]
+pub struct NonZero<T>(T);
+
+/// An optional nonzero integer that takes up the same space as the integer itself, with zero standing in for `None`.
+///
+/// The `NonZero*` types can't be zeroed, so they can't be used in a [`Yule`](crate::Yule) directly. Every bit pattern
+/// of an `OptionNonZero` is valid though, which lets handles keep their niche on the wire.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[repr(transparent)]
+pub struct OptionNonZero<T>(Option<T>);
+
+impl<T: Copy> OptionNonZero<T> {
+    pub const fn new(value: Option<T>) -> Self {
+        Self(value)
+    }
+
+    pub const fn get(self) -> Option<T> {
+        self.0
+    }
+}
+
+impl<T> Default for OptionNonZero<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<T> From<Option<T>> for OptionNonZero<T> {
+    fn from(value: Option<T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> From<T> for OptionNonZero<T> {
+    fn from(value: T) -> Self {
+        Self(Some(value))
+    }
+}
+
+impl<T> From<OptionNonZero<T>> for Option<T> {
+    fn from(value: OptionNonZero<T>) -> Self {
+        value.0
+    }
+}
+
+macro_rules! option_nonzero_yule_impl {
+    ($ty:ident) => {
+        // the niche is what makes every bit pattern valid, so make sure it's there
+        const _: () = assert!(size_of::<Option<$ty>>() == size_of::<$ty>());
+
+        impl<C: ?Sized> bytecheck::CheckBytes<C> for OptionNonZero<$ty> {
+            type Error = core::convert::Infallible;
+
+            #[inline]
+            unsafe fn check_bytes<'a>(
+                value: *const Self,
+                _: &mut C,
+            ) -> Result<&'a Self, Self::Error> {
+                Ok(&*value)
+            }
+        }
+
+        unsafe impl crate::Yule for OptionNonZero<$ty> {}
+    };
+}
+
+option_nonzero_yule_impl! { NonZeroU8 }
+option_nonzero_yule_impl! { NonZeroI8 }
+option_nonzero_yule_impl! { NonZeroU16le }
+option_nonzero_yule_impl! { NonZeroU32le }
+option_nonzero_yule_impl! { NonZeroU64le }
+option_nonzero_yule_impl! { NonZeroU128le }
+option_nonzero_yule_impl! { NonZeroI16le }
+option_nonzero_yule_impl! { NonZeroI32le }
+option_nonzero_yule_impl! { NonZeroI64le }
+option_nonzero_yule_impl! { NonZeroI128le }
+option_nonzero_yule_impl! { NonZeroU16be }
+option_nonzero_yule_impl! { NonZeroU32be }
+option_nonzero_yule_impl! { NonZeroU64be }
+option_nonzero_yule_impl! { NonZeroU128be }
+option_nonzero_yule_impl! { NonZeroI16be }
+option_nonzero_yule_impl! { NonZeroI32be }
+option_nonzero_yule_impl! { NonZeroI64be }
+option_nonzero_yule_impl! { NonZeroI128be }
+
+macro_rules! nonzero_yule_impl {
+    ($ty:ty, $int:ty, $nz:ty) => {
+        impl NonZero<$ty> {
+            pub fn new(value: $nz) -> Self {
+                Self(<$ty>::from(value.get()))
+            }
+
+            /// Returns the integer, or `None` if it hasn't been set. Validated values are never `None`.
+            pub fn get(self) -> Option<$nz> {
+                <$nz>::new(<$int>::from(self.0))
+            }
+        }
+
+        impl From<$nz> for NonZero<$ty> {
+            fn from(value: $nz) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl<C: ?Sized> bytecheck::CheckBytes<C> for NonZero<$ty> {
+            type Error = NonZeroCheckError;
+
+            #[inline]
+            unsafe fn check_bytes<'a>(
+                value: *const Self,
+                _: &mut C,
+            ) -> Result<&'a Self, Self::Error> {
+                if <$int>::from((*value).0) == 0 {
+                    return Err(NonZeroCheckError::IsZero);
+                }
+                Ok(&*value)
+            }
+        }
+
+        unsafe impl crate::Yule for NonZero<$ty> {
+            const ASSERT_LAYOUT: () = assert!(size_of::<Self>() == size_of::<$ty>());
+        }
+    };
+}
+
+nonzero_yule_impl! { u8, u8, NonZeroU8 }
+nonzero_yule_impl! { i8, i8, NonZeroI8 }
+nonzero_yule_impl! { u16le, u16, NonZeroU16 }
+nonzero_yule_impl! { u32le, u32, NonZeroU32 }
+nonzero_yule_impl! { u64le, u64, NonZeroU64 }
+nonzero_yule_impl! { u128le, u128, NonZeroU128 }
+nonzero_yule_impl! { i16le, i16, NonZeroI16 }
+nonzero_yule_impl! { i32le, i32, NonZeroI32 }
+nonzero_yule_impl! { i64le, i64, NonZeroI64 }
+nonzero_yule_impl! { i128le, i128, NonZeroI128 }
+nonzero_yule_impl! { u16be, u16, NonZeroU16 }
+nonzero_yule_impl! { u32be, u32, NonZeroU32 }
+nonzero_yule_impl! { u64be, u64, NonZeroU64 }
+nonzero_yule_impl! { u128be, u128, NonZeroU128 }
+nonzero_yule_impl! { i16be, i16, NonZeroI16 }
+nonzero_yule_impl! { i32be, i32, NonZeroI32 }
+nonzero_yule_impl! { i64be, i64, NonZeroI64 }
+nonzero_yule_impl! { i128be, i128, NonZeroI128 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Yule;
+
+    #[test]
+    fn zero_is_rejected() {
+        #[repr(C, align(8))]
+        struct Aligned([u8; 8]);
+
+        let value = NonZero::<u64le>::new(NonZeroU64::new(7).unwrap());
+        assert_eq!(value.get(), NonZeroU64::new(7));
+        assert_eq!(NonZero::<u64le>::default().get(), None);
+        let mut buf = Aligned([0; 8]);
+        assert!(NonZero::<u64le>::check(&buf.0).is_err());
+        buf.0.copy_from_slice(value.as_slice());
+        assert_eq!(
+            NonZero::<u64le>::check(&buf.0).unwrap().get(),
+            NonZeroU64::new(7)
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    mem::{size_of, ManuallyDrop, MaybeUninit},
+    ptr::addr_of,
+};
+
+use bytecheck::CheckBytes;
+
+use crate::{CheckError, PathCheckError, PathSegment};
+
+const NONE: [u8; 8] = [0; 8];
+const SOME: [u8; 8] = [1, 0, 0, 0, 0, 0, 0, 0];
+
+/// An optional value with an explicit tag, whose payload is kept zeroed while it is `None`.
+///
+/// The tag takes 8 bytes, so that the value never needs padding in front of it. The value
+/// must be at most 8 byte aligned.
+///
+/// Use [`Option::as_ref`] or the `From` impls to get a [`core::option::Option`] back out.
+#[repr(C)]
+pub struct Option<T> {
+    tag: Tag<T>,
+    value: MaybeUninit<T>,
+}
+
+/// 8 bytes that are only as aligned as `T`, so that the tag doesn't make the option more aligned than its value
+#[repr(C)]
+union Tag<T> {
+    bytes: [u8; 8],
+    _align: ManuallyDrop<[T; 0]>,
+}
+
+impl<T> Tag<T> {
+    const fn new(bytes: [u8; 8]) -> Self {
+        Self { bytes }
+    }
+
+    const fn get(&self) -> [u8; 8] {
+        // Safety: every tag is created from its bytes, and `_align` has none
+        unsafe { self.bytes }
+    }
+}
+
+impl<T> Option<T> {
+    pub const fn none() -> Self {
+        Self {
+            tag: Tag::new(NONE),
+            value: MaybeUninit::zeroed(),
+        }
+    }
+
+    pub const fn some(value: T) -> Self {
+        Self {
+            tag: Tag::new(SOME),
+            value: MaybeUninit::new(value),
+        }
+    }
+
+    pub const fn is_some(&self) -> bool {
+        self.tag.get()[0] == SOME[0]
+    }
+
+    pub const fn is_none(&self) -> bool {
+        self.tag.get()[0] == NONE[0]
+    }
+
+    pub fn as_ref(&self) -> core::option::Option<&T> {
+        if self.is_some() {
+            Some(unsafe { self.value.assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    pub fn as_mut(&mut self) -> core::option::Option<&mut T> {
+        if self.is_some() {
+            Some(unsafe { self.value.assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Takes the value out, leaving `None` in its place
+    pub fn take(&mut self) -> core::option::Option<T> {
+        if self.is_none() {
+            return None;
+        }
+        self.tag = Tag::new(NONE);
+        let value = core::mem::replace(&mut self.value, MaybeUninit::zeroed());
+        Some(unsafe { value.assume_init() })
+    }
+
+    /// Puts `value` in, returning the old value if there was one
+    pub fn replace(&mut self, value: T) -> core::option::Option<T> {
+        let old = self.take();
+        self.value.write(value);
+        self.tag = Tag::new(SOME);
+        old
+    }
+}
+
+impl<T> Default for Option<T> {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl<T: Clone> Clone for Option<T> {
+    fn clone(&self) -> Self {
+        self.as_ref().cloned().into()
+    }
+}
+
+impl<T> From<core::option::Option<T>> for Option<T> {
+    fn from(value: core::option::Option<T>) -> Self {
+        match value {
+            Some(value) => Self::some(value),
+            None => Self::none(),
+        }
+    }
+}
+
+impl<T: Clone> From<core::option::Option<&T>> for Option<T> {
+    fn from(value: core::option::Option<&T>) -> Self {
+        value.cloned().into()
+    }
+}
+
+impl<T> From<Option<T>> for core::option::Option<T> {
+    fn from(mut value: Option<T>) -> Self {
+        value.take()
+    }
+}
+
+impl<'a, T> From<&'a Option<T>> for core::option::Option<&'a T> {
+    fn from(value: &'a Option<T>) -> Self {
+        value.as_ref()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Option<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_ref().fmt(f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Option<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl<T: Eq> Eq for Option<T> {}
+
+impl<T: Hash> Hash for Option<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state)
+    }
+}
+
+impl<C, T: CheckBytes<C>> CheckBytes<C> for Option<T>
+where
+    T::Error: CheckError,
+{
+    type Error = PathCheckError;
+    unsafe fn check_bytes<'__bytecheck>(
+        value: *const Self,
+        context: &mut C,
+    ) -> ::core::result::Result<&'__bytecheck Self, PathCheckError> {
+        match *(addr_of!((*value).tag) as *const [u8; 8]) {
+            NONE => {
+                // Kept zeroed while `None`, so that nothing leaks out in a message's padding
+                let payload = addr_of!((*value).value) as *const u8;
+                if (0..size_of::<T>()).any(|i| *payload.add(i) != 0) {
+                    return Err(PathCheckError::at(PathSegment::Field("value")));
+                }
+            }
+            SOME => {
+                T::check_bytes(addr_of!((*value).value) as *const T, context)
+                    .map_err(|e| PathCheckError::within(PathSegment::Field("value"), &e))?;
+            }
+            _ => return Err(PathCheckError::at(PathSegment::Field("tag"))),
+        }
+        Ok(&*value)
+    }
+}
+
+unsafe impl<T: crate::Yule> crate::Yule for Option<T> {
+    const ASSERT_LAYOUT: () = assert!(
+        size_of::<Self>() == size_of::<[u8; 8]>() + size_of::<T>(),
+        "the value of an Option must be at most 8 byte aligned"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{u32le, u64le};
+    use crate::{ValidationError, Yule};
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; 16]);
+
+    #[test]
+    fn tag_pads_out_to_the_value() {
+        assert_eq!(size_of::<Option<u8>>(), 9);
+        assert_eq!(size_of::<Option<u32le>>(), 12);
+        assert_eq!(size_of::<Option<u64le>>(), 16);
+        assert_eq!(core::mem::align_of::<Option<u32le>>(), 4);
+    }
+
+    #[test]
+    fn none_is_zeroed() {
+        let mut value = Option::some(u32le::from(u32::MAX));
+        assert_eq!(
+            value.as_slice(),
+            &[1, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(value.take().map(u32::from), Some(u32::MAX));
+        assert_eq!(value.as_slice(), &[0; 12]);
+        assert_eq!(Option::<u64le>::none().as_slice(), &[0; 16]);
+    }
+
+    #[test]
+    fn checks_the_tag_and_value() {
+        let mut buf = Aligned([0; 16]);
+        assert!(Option::<u64le>::from_slice(&buf.0).unwrap().is_none());
+        buf.0[0] = 1;
+        buf.0[8] = 7;
+        let value = Option::<u64le>::from_slice(&buf.0).unwrap();
+        assert_eq!(value.as_ref().copied().map(u64::from), Some(7));
+        buf.0[0] = 2;
+        assert!(Option::<u64le>::from_slice(&buf.0).is_none());
+        buf.0[0] = 1;
+        buf.0[3] = 1;
+        assert!(Option::<u64le>::from_slice(&buf.0).is_none());
+        buf.0[3] = 0;
+        buf.0[8] = 2;
+        assert!(Option::<bool>::from_slice(&buf.0[..9]).is_none());
+    }
+
+    #[test]
+    fn none_must_have_a_zeroed_payload() {
+        let mut buf = Aligned([0; 16]);
+        buf.0[8] = 7;
+        match Option::<u64le>::check(&buf.0).map(drop) {
+            Err(ValidationError::Content { path }) => {
+                assert_eq!(path.segments(), &[PathSegment::Field("value")])
+            }
+            res => panic!("expected a content error, got {:?}", res),
+        }
+    }
+}
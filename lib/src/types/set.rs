@@ -0,0 +1,151 @@
+use super::Vec;
+use core::{fmt, ops::Deref, ptr::addr_of};
+
+use bytecheck::CheckBytes;
+
+use crate::{CheckError, PathCheckError, PathSegment};
+
+/// A fixed-capacity set, that keeps its items sorted so lookups can binary search.
+///
+/// Validation checks that the items are sorted and unique.
+#[repr(C)]
+pub struct Set<T, const N: usize> {
+    items: Vec<T, N>,
+}
+
+impl<T, const N: usize> Set<T, N> {
+    pub const fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear()
+    }
+}
+
+impl<T: Ord, const N: usize> Set<T, N> {
+    pub fn contains(&self, item: &T) -> bool {
+        self.items.binary_search(item).is_ok()
+    }
+
+    /// Adds `item` to the set, returning whether it wasn't already there.
+    /// Returns `item` back if the set is full.
+    pub fn insert(&mut self, item: T) -> Result<bool, T> {
+        match self.items.binary_search(&item) {
+            Ok(_) => Ok(false),
+            Err(index) => {
+                self.items.insert(index, item)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Removes `item` from the set, returning whether it was there
+    pub fn remove(&mut self, item: &T) -> bool {
+        match self.items.binary_search(item) {
+            Ok(index) => {
+                self.items.remove(index);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+impl<T, const N: usize> Default for Set<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Deref for Set<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.items.as_slice()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for Set<T, N> {
+    fn clone(&self) -> Self {
+        Self {
+            items: self.items.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for Set<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for Set<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.items == other.items
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for Set<T, N> {}
+
+/// Paths lead to the invalid item by index. An item that isn't greater than the previous one is invalid.
+impl<C, T: CheckBytes<C> + Ord, const N: usize> CheckBytes<C> for Set<T, N>
+where
+    T::Error: CheckError,
+{
+    type Error = PathCheckError;
+    unsafe fn check_bytes<'__bytecheck>(
+        value: *const Self,
+        context: &mut C,
+    ) -> ::core::result::Result<&'__bytecheck Self, PathCheckError> {
+        let items = Vec::<T, N>::check_bytes(addr_of!((*value).items), context)?;
+        if let Some(index) = items.windows(2).position(|w| w[0] >= w[1]) {
+            return Err(PathCheckError::at(PathSegment::Index(index + 1)));
+        }
+        Ok(&*value)
+    }
+}
+
+unsafe impl<T: crate::Yule + Ord, const N: usize> crate::Yule for Set<T, N> {
+    const ASSERT_LAYOUT: () = <Vec<T, N> as crate::Yule>::ASSERT_LAYOUT;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ValidationError, Yule};
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; 16]);
+
+    #[test]
+    fn keeps_items_sorted() {
+        let mut set = Set::<u8, 2>::new();
+        assert_eq!(set.insert(3), Ok(true));
+        assert_eq!(set.insert(1), Ok(true));
+        assert_eq!(set.insert(3), Ok(false));
+        assert_eq!(set.insert(2), Err(2));
+        assert_eq!(&set[..], &[1, 3]);
+        assert!(set.remove(&1));
+        assert!(!set.contains(&1));
+    }
+
+    #[test]
+    fn checks_items_are_sorted_and_unique() {
+        let mut buf = Aligned([0; 16]);
+        buf.0[0] = 3;
+        buf.0[8..11].copy_from_slice(&[1, 2, 5]);
+        assert!(Set::<u8, 8>::check(&buf.0).unwrap().contains(&5));
+        buf.0[10] = 2;
+        match Set::<u8, 8>::check(&buf.0).map(drop) {
+            Err(ValidationError::Content { path }) => {
+                assert_eq!(path.segments(), &[PathSegment::Index(2)])
+            }
+            res => panic!("expected a content error, got {:?}", res),
+        }
+    }
+}
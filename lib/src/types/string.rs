@@ -0,0 +1,208 @@
+use super::u64le;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    ptr::addr_of,
+    str,
+};
+
+use bytecheck::CheckBytes;
+
+use crate::{PathCheckError, PathSegment};
+
+/// A fixed-capacity UTF-8 string.
+///
+/// `N` must be a multiple of 8, so that the string doesn't need any padding after its buffer.
+#[repr(C)]
+pub struct String<const N: usize> {
+    len: u64le,
+    buf: [u8; N],
+}
+
+/// Returned when there isn't enough room left in a [`String`] or [`Vec`](super::Vec)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError;
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("not enough capacity left")
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for CapacityError {}
+
+impl<const N: usize> String<N> {
+    pub const fn new() -> Self {
+        Self {
+            len: u64le::new(0),
+            buf: [0; N],
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = u64::from(self.len) as usize;
+        // Safety: every method that writes to `buf` keeps `buf[..len]` valid UTF-8,
+        // and `check_bytes` verifies it for strings that came off the wire
+        unsafe { str::from_utf8_unchecked(&self.buf[..len]) }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn push_str(&mut self, s: &str) -> Result<(), CapacityError> {
+        let len = u64::from(self.len) as usize;
+        let end = len + s.len();
+        if end > N {
+            return Err(CapacityError);
+        }
+        self.buf[len..end].copy_from_slice(s.as_bytes());
+        self.len = u64le::new(end as u64);
+        Ok(())
+    }
+
+    pub fn push(&mut self, c: char) -> Result<(), CapacityError> {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    pub fn clear(&mut self) {
+        self.buf = [0; N];
+        self.len = u64le::new(0);
+    }
+}
+
+impl<const N: usize> Default for String<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Clone for String<N> {
+    fn clone(&self) -> Self {
+        Self {
+            len: self.len,
+            buf: self.buf,
+        }
+    }
+}
+
+impl<const N: usize> Deref for String<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for String<N> {
+    type Error = CapacityError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut s = Self::new();
+        s.push_str(value)?;
+        Ok(s)
+    }
+}
+
+impl<const N: usize> fmt::Debug for String<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<const N: usize> fmt::Display for String<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<const N: usize> PartialEq for String<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const N: usize> Eq for String<N> {}
+
+impl<const N: usize> PartialEq<str> for String<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for String<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<const N: usize> Hash for String<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl<C, const N: usize> CheckBytes<C> for String<N> {
+    type Error = PathCheckError;
+    unsafe fn check_bytes<'__bytecheck>(
+        value: *const Self,
+        _context: &mut C,
+    ) -> ::core::result::Result<&'__bytecheck Self, PathCheckError> {
+        let len = u64::from(*addr_of!((*value).len));
+        if len > N as u64 {
+            return Err(PathCheckError::at(PathSegment::Field("len")));
+        }
+        let buf = &*addr_of!((*value).buf);
+        str::from_utf8(&buf[..len as usize])
+            .map_err(|_| PathCheckError::at(PathSegment::Field("buf")))?;
+        Ok(&*value)
+    }
+}
+
+unsafe impl<const N: usize> crate::Yule for String<N> {
+    const ASSERT_LAYOUT: () = assert!(
+        N.is_multiple_of(8),
+        "String capacity must be a multiple of 8"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FieldPath, ValidationError, Yule};
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; 16]);
+
+    fn path_of(buf: &[u8]) -> FieldPath {
+        match String::<8>::check(buf).map(drop) {
+            Err(ValidationError::Content { path }) => path,
+            res => panic!("expected a content error, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn pushes_up_to_capacity() {
+        let mut s = String::<8>::try_from("piton").unwrap();
+        assert_eq!(s.push('é'), Ok(()));
+        assert_eq!(s, "pitoné");
+        assert_eq!(s.push('é'), Err(CapacityError));
+        assert_eq!(s, "pitoné");
+        s.clear();
+        assert_eq!(s.as_slice(), &[0; 16]);
+    }
+
+    #[test]
+    fn checks_the_len_and_utf8() {
+        let mut buf = Aligned([0; 16]);
+        buf.0[0] = 2;
+        buf.0[8..10].copy_from_slice(b"hi");
+        assert_eq!(String::<8>::check(&buf.0).unwrap(), "hi");
+        buf.0[9] = 0xff;
+        assert_eq!(path_of(&buf.0).segments(), &[PathSegment::Field("buf")]);
+        buf.0[0] = 9;
+        assert_eq!(path_of(&buf.0).segments(), &[PathSegment::Field("len")]);
+    }
+}
@@ -0,0 +1,354 @@
+use super::{u32le, CapacityError};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::{align_of, size_of, size_of_val},
+    ptr::addr_of,
+    slice, str,
+};
+
+use bytecheck::CheckBytes;
+
+use crate::{CheckError, PathCheckError, PathSegment};
+
+/// The [`bytecheck`] context every [`Yule`](crate::Yule) is validated with.
+///
+/// It points at the trailing region of the message, which is where the elements of
+/// variable-length fields like [`Slice`] and [`Str`] live.
+pub struct TailContext {
+    ptr: *const u8,
+    len: usize,
+    /// How many more bytes of elements can be checked, so that slices pointing at the same
+    /// elements over and over can't make validation take forever
+    budget: usize,
+}
+
+impl TailContext {
+    pub fn new(tail: &[u8]) -> Self {
+        Self {
+            ptr: tail.as_ptr(),
+            len: tail.len(),
+            budget: tail.len().saturating_mul(8),
+        }
+    }
+
+    /// Returns where the `len` elements starting `offset` bytes into the tail are, if they
+    /// fit and are aligned.
+    ///
+    /// If `field` lies inside the tail its elements must end before it does, so that
+    /// nested slices can't point back at themselves.
+    fn locate<T>(&mut self, field: *const u8, offset: usize, len: usize) -> Option<*const T> {
+        let bytes = len.checked_mul(size_of::<T>())?;
+        let end = offset.checked_add(bytes)?;
+        let field_offset = (field as usize).wrapping_sub(self.ptr as usize);
+        let limit = if field_offset < self.len {
+            field_offset
+        } else {
+            self.len
+        };
+        if end > limit {
+            return None;
+        }
+        self.budget = self.budget.checked_sub(bytes)?;
+        // Safety: `offset <= end <= self.len`, so this stays inside the tail
+        let ptr = unsafe { self.ptr.add(offset) } as *const T;
+        (ptr.align_offset(align_of::<T>()) == 0).then_some(ptr)
+    }
+}
+
+/// A variable-length run of `T`s, stored in the trailing region of the message.
+///
+/// On the wire it is an offset from the start of the trailing region and a length.
+/// Use [`Slice::get`] with the message's trailing region to read the elements, and
+/// [`TailWriter::push`] to write them.
+#[repr(C)]
+pub struct Slice<T> {
+    offset: u32le,
+    len: u32le,
+    _phantom: PhantomData<T>,
+}
+
+/// A variable-length run of bytes, stored in the trailing region of the message
+pub type Bytes = Slice<u8>;
+
+impl<T> Slice<T> {
+    pub const fn empty() -> Self {
+        Self {
+            offset: u32le::new(0),
+            len: u32le::new(0),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        u32::from(self.len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Looks the elements up in `tail`, the trailing region of the message this slice is part of.
+    ///
+    /// Returns `None` if they don't fit in `tail`, are misaligned, or aren't valid `T`s.
+    pub fn get<'a>(&self, tail: &'a [u8]) -> Option<&'a [T]>
+    where
+        T: CheckBytes<TailContext>,
+        T::Error: CheckError,
+    {
+        let mut context = TailContext::new(tail);
+        // Safety: `self` is a valid reference
+        unsafe { Self::check_elements(self, &mut context) }
+            .ok()
+            // Safety: `check_elements` checked the elements lie inside `tail` and are valid
+            .map(|ptr| unsafe { slice::from_raw_parts(ptr, self.len()) })
+    }
+
+    /// Checks the elements, returning where they start. They're invalid at `len` if they don't fit
+    /// in the tail, and at their index if one of them isn't a valid `T`.
+    unsafe fn check_elements(
+        value: *const Self,
+        context: &mut TailContext,
+    ) -> Result<*const T, PathCheckError>
+    where
+        T: CheckBytes<TailContext>,
+        T::Error: CheckError,
+    {
+        let offset = u32::from(*addr_of!((*value).offset)) as usize;
+        let len = u32::from(*addr_of!((*value).len)) as usize;
+        let ptr = context
+            .locate::<T>(value as *const u8, offset, len)
+            .ok_or(PathCheckError::at(PathSegment::Field("len")))?;
+        for index in 0..len {
+            T::check_bytes(ptr.add(index), context)
+                .map_err(|e| PathCheckError::within(PathSegment::Index(index), &e))?;
+        }
+        Ok(ptr)
+    }
+}
+
+impl<T> Default for Slice<T> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<T> Clone for Slice<T> {
+    fn clone(&self) -> Self {
+        Self {
+            offset: self.offset,
+            len: self.len,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Slice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Slice")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<T> PartialEq for Slice<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset && self.len == other.len
+    }
+}
+
+impl<T> Eq for Slice<T> {}
+
+impl<T> Hash for Slice<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.offset.hash(state);
+        self.len.hash(state);
+    }
+}
+
+impl<T: CheckBytes<TailContext>> CheckBytes<TailContext> for Slice<T>
+where
+    T::Error: CheckError,
+{
+    type Error = PathCheckError;
+    unsafe fn check_bytes<'__bytecheck>(
+        value: *const Self,
+        context: &mut TailContext,
+    ) -> ::core::result::Result<&'__bytecheck Self, PathCheckError> {
+        Self::check_elements(value, context)?;
+        Ok(&*value)
+    }
+}
+
+unsafe impl<T: crate::Yule> crate::Yule for Slice<T> {}
+
+/// A variable-length UTF-8 string, stored in the trailing region of the message
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Str(Bytes);
+
+impl Str {
+    pub const fn empty() -> Self {
+        Self(Slice::empty())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Looks the string up in `tail`, the trailing region of the message this string is part of.
+    ///
+    /// Returns `None` if it doesn't fit in `tail` or isn't valid UTF-8.
+    pub fn get<'a>(&self, tail: &'a [u8]) -> Option<&'a str> {
+        str::from_utf8(self.0.get(tail)?).ok()
+    }
+}
+
+impl fmt::Debug for Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Str")
+            .field("offset", &self.0.offset)
+            .field("len", &self.0.len)
+            .finish()
+    }
+}
+
+impl CheckBytes<TailContext> for Str {
+    type Error = PathCheckError;
+    unsafe fn check_bytes<'__bytecheck>(
+        value: *const Self,
+        context: &mut TailContext,
+    ) -> ::core::result::Result<&'__bytecheck Self, PathCheckError> {
+        let value = value as *const Bytes;
+        let ptr = Bytes::check_elements(value, context)?;
+        let bytes = slice::from_raw_parts(ptr, (*value).len());
+        str::from_utf8(bytes).map_err(|_| PathCheckError::at(PathSegment::Field("len")))?;
+        Ok(&*(value as *const Self))
+    }
+}
+
+unsafe impl crate::Yule for Str {}
+
+/// Appends the elements of variable-length fields to the trailing region of a message
+/// that is being written.
+///
+/// Transports hand these out from [`BufW::split_tail`](crate::BufW::split_tail), and only
+/// send the part of the region that was used.
+pub struct TailWriter<'a> {
+    buf: &'a mut [u8],
+    /// `None` for writers with no capacity, which never write anything
+    used: Option<&'a mut usize>,
+}
+
+impl<'a> TailWriter<'a> {
+    /// Creates a writer that appends to `buf` after its first `used` bytes, and keeps `used` up to date
+    pub fn new(buf: &'a mut [u8], used: &'a mut usize) -> Self {
+        Self {
+            buf,
+            used: Some(used),
+        }
+    }
+
+    /// Creates a writer with no capacity, for transports that don't send a trailing region
+    pub fn empty() -> Self {
+        Self {
+            buf: &mut [],
+            used: None,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.as_deref().copied().unwrap_or(0)
+    }
+
+    /// Copies `items` onto the end of the trailing region, returning the [`Slice`] that points at them
+    pub fn push<T: crate::Yule>(&mut self, items: &[T]) -> Result<Slice<T>, CapacityError> {
+        let used = self.used();
+        let pad = self.buf[used..].as_ptr().align_offset(align_of::<T>());
+        let start = used.checked_add(pad).ok_or(CapacityError)?;
+        let end = start
+            .checked_add(size_of_val(items))
+            .filter(|&end| end <= self.buf.len() && end <= u32::MAX as usize)
+            .ok_or(CapacityError)?;
+        self.buf[used..start].fill(0);
+        for (chunk, item) in self.buf[start..end]
+            .chunks_exact_mut(size_of::<T>().max(1))
+            .zip(items)
+        {
+            chunk.copy_from_slice(item.as_slice());
+        }
+        if let Some(used) = self.used.as_deref_mut() {
+            *used = end;
+        }
+        Ok(Slice {
+            offset: u32le::new(start as u32),
+            len: u32le::new(items.len() as u32),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Copies `s` onto the end of the trailing region, returning the [`Str`] that points at it
+    pub fn push_str(&mut self, s: &str) -> Result<Str, CapacityError> {
+        self.push(s.as_bytes()).map(Str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FieldPath, ValidationError, Yule};
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; 32]);
+
+    fn path_of(buf: &[u8]) -> FieldPath {
+        match Slice::<u32le>::check(buf).map(drop) {
+            Err(ValidationError::Content { path }) => path,
+            res => panic!("expected a content error, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn pushes_aligned_elements_up_to_capacity() {
+        let mut buf = Aligned([0xff; 32]);
+        let mut used = 0;
+        let mut tail = TailWriter::new(&mut buf.0[..16], &mut used);
+        let name = tail.push_str("hi").unwrap();
+        let nums = tail.push(&[u32le::new(1), u32le::new(2)]).unwrap();
+        assert_eq!(tail.used(), 12);
+        assert_eq!(tail.push(&[0u8; 8]), Err(CapacityError));
+        assert_eq!(used, 12);
+        // The padding before the aligned elements is zeroed
+        assert_eq!(buf.0[2..4], [0, 0]);
+        assert_eq!(name.get(&buf.0[..used]), Some("hi"));
+        assert_eq!(
+            nums.get(&buf.0[..used]),
+            Some(&[u32le::new(1), u32le::new(2)][..])
+        );
+        assert_eq!(TailWriter::empty().push_str("hi"), Err(CapacityError));
+    }
+
+    #[test]
+    fn checks_elements_lie_inside_the_tail() {
+        let mut buf = Aligned([0; 32]);
+        let mut used = 0;
+        let mut tail = TailWriter::new(&mut buf.0[8..], &mut used);
+        let slice = tail.push(&[u32le::new(7); 2]).unwrap();
+        buf.0[..8].copy_from_slice(slice.as_slice());
+        let checked = Slice::<u32le>::check(&buf.0).unwrap();
+        assert_eq!(checked.get(&buf.0[8..]), Some(&[u32le::new(7); 2][..]));
+        buf.0[4] = 7;
+        assert_eq!(path_of(&buf.0).segments(), &[PathSegment::Field("len")]);
+        assert_eq!(slice.get(&buf.0[8..12]), None);
+    }
+}
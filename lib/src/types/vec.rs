@@ -1,13 +1,22 @@
-use super::u64le;
+use super::{u64le, CapacityError};
 use core::{
-    mem::MaybeUninit,
+    hash::{Hash, Hasher},
+    mem::{align_of, size_of, MaybeUninit},
     ops::{Deref, DerefMut},
-    ptr::addr_of,
+    ptr::{self, addr_of},
     slice,
 };
 
-use bytecheck::{CheckBytes, StructCheckError};
+use bytecheck::CheckBytes;
 
+use crate::{CheckError, PathCheckError, PathSegment};
+
+/// A fixed-capacity vector.
+///
+/// Slots past `len` are always zeroed, so the whole vector can be sent as-is. The elements
+/// must be at most 8 byte aligned, and `N` of them must fill a multiple of 8 bytes, so that
+/// the vector doesn't need any padding.
+#[repr(C)]
 pub struct Vec<T, const N: usize> {
     len: u64le,
     buf: [MaybeUninit<T>; N],
@@ -19,34 +28,59 @@ impl<T: core::fmt::Debug, const N: usize> core::fmt::Debug for Vec<T, N> {
     }
 }
 
-impl<C, T: CheckBytes<C>, const N: usize> CheckBytes<C> for Vec<T, N> {
-    type Error = StructCheckError;
+impl<C, T: CheckBytes<C>, const N: usize> CheckBytes<C> for Vec<T, N>
+where
+    T::Error: CheckError,
+{
+    type Error = PathCheckError;
     unsafe fn check_bytes<'__bytecheck>(
         value: *const Self,
         context: &mut C,
-    ) -> ::core::result::Result<&'__bytecheck Self, StructCheckError> {
-        <u64le as CheckBytes<C>>::check_bytes(addr_of!((*value).len), context)
-            .map_err(|_e| StructCheckError { field_name: "len" })?;
+    ) -> ::core::result::Result<&'__bytecheck Self, PathCheckError> {
+        let len = u64::from(*addr_of!((*value).len));
+        if len > N as u64 {
+            return Err(PathCheckError::at(PathSegment::Field("len")));
+        }
         let bytes = (*value).buf.as_ptr() as *const T;
-        for index in 0..N {
+        for index in 0..len as usize {
             let el_bytes = bytes.add(index);
             T::check_bytes(el_bytes, context)
-                .map_err(|_| StructCheckError { field_name: "buf" })?;
+                .map_err(|e| PathCheckError::within(PathSegment::Index(index), &e))?;
+        }
+        // Kept zeroed past `len`, so that nothing leaks out in the unused slots
+        let unused = bytes.add(len as usize) as *const u8;
+        let unused_len = (N - len as usize) * size_of::<T>();
+        if let Some(i) = (0..unused_len).find(|&i| *unused.add(i) != 0) {
+            let index = len as usize + i / size_of::<T>();
+            return Err(PathCheckError::at(PathSegment::Index(index)));
         }
         Ok(&*value)
     }
 }
 
+unsafe impl<T: crate::Yule, const N: usize> crate::Yule for Vec<T, N> {
+    const ASSERT_LAYOUT: () = assert!(
+        align_of::<T>() <= 8 && (N * size_of::<T>()).is_multiple_of(8),
+        "Vec elements must be at most 8 byte aligned and fill a multiple of 8 bytes"
+    );
+}
+
 impl<T: PartialEq, const N: usize> PartialEq for Vec<T, N> {
     fn eq(&self, other: &Self) -> bool {
         self.as_slice() == other.as_slice()
     }
 }
 
-impl<T: PartialEq, const N: usize> Eq for Vec<T, N> {}
+impl<T: Eq, const N: usize> Eq for Vec<T, N> {}
+
+impl<T: Hash, const N: usize> Hash for Vec<T, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state)
+    }
+}
 
 impl<T, const N: usize> Vec<T, N> {
-    const ELEM: MaybeUninit<T> = MaybeUninit::uninit();
+    const ELEM: MaybeUninit<T> = MaybeUninit::zeroed();
     const INIT: [MaybeUninit<T>; N] = [Self::ELEM; N];
 
     pub const fn new() -> Self {
@@ -56,24 +90,109 @@ impl<T, const N: usize> Vec<T, N> {
         }
     }
 
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    fn len_usize(&self) -> usize {
+        u64::from(self.len) as usize
+    }
+
     pub fn push(&mut self, elem: T) -> Result<(), T> {
-        if u64::from(self.len) >= (N as u64) {
+        if self.len_usize() >= N {
             return Err(elem);
         }
-        self.buf[u64::from(self.len) as usize].write(elem);
+        self.buf[self.len_usize()].write(elem);
         self.len += 1;
         Ok(())
     }
 
     pub fn push_unchecked(&mut self, elem: T) {
-        self.buf[u64::from(self.len) as usize].write(elem);
+        self.buf[self.len_usize()].write(elem);
         self.len += 1;
     }
 
-    pub fn as_slice(&self) -> &[T] {
+    pub fn pop(&mut self) -> Option<T> {
+        let len = self.len_usize().checked_sub(1)?;
+        self.len = u64le::new(len as u64);
+        let elem = unsafe { self.buf[len].assume_init_read() };
+        self.buf[len] = Self::ELEM;
+        Some(elem)
+    }
+
+    /// Drops every element past `len`, leaving the first `len` in place
+    pub fn truncate(&mut self, len: usize) {
+        while self.len_usize() > len {
+            self.pop();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// Inserts `elem` at `index`, shifting every element after it to the right.
+    /// Returns `elem` back if the vector is full.
+    ///
+    /// # Panics
+    /// Panics if `index > len`
+    pub fn insert(&mut self, index: usize, elem: T) -> Result<(), T> {
+        let len = self.len_usize();
+        assert!(
+            index <= len,
+            "insertion index {} is past len {}",
+            index,
+            len
+        );
+        if len >= N {
+            return Err(elem);
+        }
         unsafe {
-            slice::from_raw_parts(self.buf.as_ptr() as *const T, u64::from(self.len) as usize)
+            let p = self.buf.as_mut_ptr().add(index);
+            ptr::copy(p, p.add(1), len - index);
+        }
+        self.buf[index].write(elem);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes the element at `index`, shifting every element after it to the left
+    ///
+    /// # Panics
+    /// Panics if `index >= len`
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len_usize();
+        assert!(index < len, "removal index {} is past len {}", index, len);
+        let elem = unsafe {
+            let p = self.buf.as_mut_ptr().add(index);
+            let elem = p.read().assume_init();
+            ptr::copy(p.add(1), p, len - index - 1);
+            elem
+        };
+        self.buf[len - 1] = Self::ELEM;
+        self.len = u64le::new(len as u64 - 1);
+        elem
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.buf.as_ptr() as *const T, self.len_usize()) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.buf.as_mut_ptr() as *mut T, self.len_usize()) }
+    }
+}
+
+impl<T: Clone, const N: usize> Vec<T, N> {
+    /// Clones every element of `other` onto the end of the vector, or does nothing if they don't all fit
+    pub fn extend_from_slice(&mut self, other: &[T]) -> Result<(), CapacityError> {
+        if self.len_usize() + other.len() > N {
+            return Err(CapacityError);
         }
+        for elem in other {
+            self.push_unchecked(elem.clone());
+        }
+        Ok(())
     }
 }
 
@@ -93,12 +212,7 @@ impl<T, const N: usize> Deref for Vec<T, N> {
 
 impl<T, const N: usize> DerefMut for Vec<T, N> {
     fn deref_mut(&mut self) -> &mut [T] {
-        unsafe {
-            slice::from_raw_parts_mut(
-                self.buf.as_mut_ptr() as *mut T,
-                u64::from(self.len) as usize,
-            )
-        }
+        self.as_mut_slice()
     }
 }
 
@@ -111,3 +225,101 @@ impl<T: Clone, const N: usize> Clone for Vec<T, N> {
         new
     }
 }
+
+impl<T: Clone, const N: usize> TryFrom<&[T]> for Vec<T, N> {
+    type Error = CapacityError;
+
+    fn try_from(value: &[T]) -> Result<Self, Self::Error> {
+        let mut new = Self::new();
+        new.extend_from_slice(value)?;
+        Ok(new)
+    }
+}
+
+/// # Panics
+/// Panics if the iterator yields more than `N` elements
+impl<T, const N: usize> FromIterator<T> for Vec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut new = Self::new();
+        for elem in iter {
+            if new.push(elem).is_err() {
+                panic!("iterator yielded more than {} elements", N);
+            }
+        }
+        new
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a Vec<T, N> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut Vec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FieldPath, ValidationError, Yule};
+
+    #[repr(C, align(8))]
+    struct Aligned([u8; 16]);
+
+    fn path_of(buf: &[u8]) -> FieldPath {
+        match Vec::<bool, 8>::check(buf).map(drop) {
+            Err(ValidationError::Content { path }) => path,
+            res => panic!("expected a content error, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn unused_slots_stay_zeroed() {
+        let mut vec = Vec::<u8, 8>::new();
+        vec.extend_from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!(vec.remove(0), 1);
+        assert_eq!(vec.pop(), Some(3));
+        assert_eq!(vec.as_slice(), &[2]);
+        assert_eq!(
+            Yule::as_slice(&vec),
+            &[1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(vec.push(4), Ok(()));
+        assert!(Vec::<u8, 1>::try_from(&[1, 2][..]).is_err());
+    }
+
+    #[test]
+    fn checks_the_len_and_each_element() {
+        let mut buf = Aligned([0; 16]);
+        buf.0[0] = 2;
+        buf.0[8..10].copy_from_slice(&[1, 0]);
+        assert_eq!(
+            Vec::<bool, 8>::check(&buf.0).unwrap().as_slice(),
+            &[true, false]
+        );
+        buf.0[9] = 2;
+        assert_eq!(path_of(&buf.0).segments(), &[PathSegment::Index(1)]);
+        buf.0[0] = 9;
+        assert_eq!(path_of(&buf.0).segments(), &[PathSegment::Field("len")]);
+    }
+
+    #[test]
+    fn rejects_unused_slots_that_arent_zeroed() {
+        let mut buf = Aligned([0; 16]);
+        buf.0[0] = 1;
+        buf.0[8] = 1;
+        assert!(Vec::<bool, 8>::check(&buf.0).is_ok());
+        buf.0[11] = 1;
+        assert_eq!(path_of(&buf.0).segments(), &[PathSegment::Index(3)]);
+    }
+}
@@ -0,0 +1,341 @@
+use core::{
+    convert::Infallible,
+    fmt,
+    mem::{align_of, size_of},
+};
+
+use bytecheck::{
+    ArrayCheckError, BoolCheckError, CharCheckError, EnumCheckError, NonZeroCheckError,
+    StructCheckError, TupleStructCheckError,
+};
+
+/// Why a slice couldn't be read as a [`Yule`](crate::Yule), returned by [`Yule::check`](crate::Yule::check)
+/// and [`Envelope::check_frame`](crate::Envelope::check_frame)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The slice is shorter than the type
+    Length { expected: usize, found: usize },
+    /// The slice doesn't start at a multiple of the type's alignment
+    Alignment { expected: usize },
+    /// The frame's tag doesn't belong to any of the envelope's variants
+    UnknownTag(u32),
+    /// The bytes aren't a valid value. `path` leads to the field that was invalid
+    Content { path: FieldPath },
+}
+
+impl ValidationError {
+    /// Adds `segment` to the front of the path of a [`ValidationError::Content`]
+    pub fn within(self, segment: PathSegment) -> Self {
+        match self {
+            ValidationError::Content { mut path } => {
+                path.push_front(segment);
+                ValidationError::Content { path }
+            }
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Length { expected, found } => {
+                write!(f, "expected at least {} bytes, found {}", expected, found)
+            }
+            ValidationError::Alignment { expected } => {
+                write!(f, "expected alignment of {}", expected)
+            }
+            ValidationError::UnknownTag(tag) => write!(f, "unknown tag {}", tag),
+            ValidationError::Content { path } if path.is_empty() => write!(f, "invalid value"),
+            ValidationError::Content { path } => write!(f, "invalid value at {}", path),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for ValidationError {}
+
+/// One step of a [`FieldPath`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A struct field or enum variant
+    Field(&'static str),
+    /// An array element or tuple field
+    Index(usize),
+}
+
+/// The fields leading to the invalid part of a value, outermost first.
+///
+/// Paths hold up to [`FieldPath::CAPACITY`] segments, the innermost ones are dropped past that.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FieldPath {
+    segments: [PathSegment; FieldPath::CAPACITY],
+    len: usize,
+}
+
+impl FieldPath {
+    pub const CAPACITY: usize = 4;
+
+    pub const fn new() -> Self {
+        Self {
+            segments: [PathSegment::Index(0); FieldPath::CAPACITY],
+            len: 0,
+        }
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments[..self.len]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push_front(&mut self, segment: PathSegment) {
+        self.segments.copy_within(..Self::CAPACITY - 1, 1);
+        self.segments[0] = segment;
+        self.len = (self.len + 1).min(Self::CAPACITY);
+    }
+
+    fn with_front(mut self, segment: PathSegment) -> Self {
+        self.push_front(segment);
+        self
+    }
+}
+
+impl Default for FieldPath {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments().iter().enumerate() {
+            match segment {
+                PathSegment::Field(name) if i == 0 => write!(f, "{}", name)?,
+                PathSegment::Field(name) => write!(f, ".{}", name)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.segments()).finish()
+    }
+}
+
+/// Implemented by the [`bytecheck`] errors of [`Yule`](crate::Yule)s, so that validation can report
+/// which field was invalid
+pub trait CheckError {
+    /// Returns the path to the invalid field, or an empty path if the value itself was invalid
+    fn path(&self) -> FieldPath;
+}
+
+impl CheckError for Infallible {
+    fn path(&self) -> FieldPath {
+        match *self {}
+    }
+}
+
+macro_rules! leaf_check_error {
+    ($($error:ty),*) => {
+        $(
+            impl CheckError for $error {
+                fn path(&self) -> FieldPath {
+                    FieldPath::new()
+                }
+            }
+        )*
+    };
+}
+
+leaf_check_error! { BoolCheckError, CharCheckError, NonZeroCheckError }
+
+impl CheckError for StructCheckError {
+    fn path(&self) -> FieldPath {
+        FieldPath::new().with_front(PathSegment::Field(self.field_name))
+    }
+}
+
+impl CheckError for TupleStructCheckError {
+    fn path(&self) -> FieldPath {
+        FieldPath::new().with_front(PathSegment::Index(self.field_index))
+    }
+}
+
+/// The [`bytecheck`] error of Piton's own types and of the structs and enums Piton generates.
+///
+/// [`StructCheckError`] only names the outermost field, so these keep the whole path instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PathCheckError {
+    path: FieldPath,
+}
+
+impl PathCheckError {
+    /// The value itself was invalid, rather than one of its fields
+    pub fn new() -> Self {
+        Self {
+            path: FieldPath::new(),
+        }
+    }
+
+    /// The value at `segment` was invalid
+    pub fn at(segment: PathSegment) -> Self {
+        Self {
+            path: FieldPath::new().with_front(segment),
+        }
+    }
+
+    /// The value at `segment` was invalid, where `inner` says
+    pub fn within(segment: PathSegment, inner: &impl CheckError) -> Self {
+        Self {
+            path: inner.path().with_front(segment),
+        }
+    }
+}
+
+impl fmt::Display for PathCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "check failed at {}", self.path)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for PathCheckError {}
+
+impl CheckError for PathCheckError {
+    fn path(&self) -> FieldPath {
+        self.path
+    }
+}
+
+impl<T> CheckError for EnumCheckError<T> {
+    fn path(&self) -> FieldPath {
+        match self {
+            EnumCheckError::InvalidStruct {
+                variant_name,
+                inner,
+            } => inner.path().with_front(PathSegment::Field(variant_name)),
+            EnumCheckError::InvalidTuple {
+                variant_name,
+                inner,
+            } => inner.path().with_front(PathSegment::Field(variant_name)),
+            EnumCheckError::InvalidTag(_) => FieldPath::new(),
+        }
+    }
+}
+
+impl<T: CheckError> CheckError for ArrayCheckError<T> {
+    fn path(&self) -> FieldPath {
+        self.error.path().with_front(PathSegment::Index(self.index))
+    }
+}
+
+/// Checks that `slice` is long enough and aligned to hold a `T`
+pub(crate) fn check_layout<T>(slice: &[u8]) -> Result<(), ValidationError> {
+    if slice.len() < size_of::<T>() {
+        return Err(ValidationError::Length {
+            expected: size_of::<T>(),
+            found: slice.len(),
+        });
+    }
+    if slice.as_ptr().align_offset(align_of::<T>()) != 0 {
+        return Err(ValidationError::Alignment {
+            expected: align_of::<T>(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Map, Option, Set, Vec};
+    use crate::Yule;
+
+    #[repr(C, align(16))]
+    struct Aligned([u8; 128]);
+
+    /// Copies `value` into an aligned buffer, lets `corrupt` change its bytes, and returns the path that `check` fails at
+    fn invalid_path<T: Yule>(value: &T, corrupt: impl FnOnce(&mut [u8])) -> FieldPath {
+        let mut buf = Aligned([0; 128]);
+        let bytes = &mut buf.0[..size_of::<T>()];
+        bytes.copy_from_slice(value.as_slice());
+        corrupt(bytes);
+        match T::check(bytes).map(drop) {
+            Err(ValidationError::Content { path }) => path,
+            res => panic!("expected a content error, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn vec_paths_lead_to_the_element() {
+        let mut vec = Vec::<Option<bool>, 8>::new();
+        vec.push(Option::some(true)).unwrap();
+        vec.push(Option::some(false)).unwrap();
+        // the length, then one 9 byte option, then the second option's tag
+        let path = invalid_path(&vec, |b| b[8 + 9 + 8] = 2);
+        assert_eq!(
+            path.segments(),
+            [PathSegment::Index(1), PathSegment::Field("value")]
+        );
+        assert_eq!(path.to_string(), "[1].value");
+        let path = invalid_path(&vec, |b| b[0] = 9);
+        assert_eq!(path.segments(), [PathSegment::Field("len")]);
+    }
+
+    #[test]
+    fn option_paths_lead_into_the_value() {
+        let mut vec = Vec::<bool, 8>::new();
+        vec.extend_from_slice(&[true; 4]).unwrap();
+        let path = invalid_path(&Option::some(vec), |b| b[8 + 8 + 3] = 2);
+        assert_eq!(
+            path.segments(),
+            [PathSegment::Field("value"), PathSegment::Index(3)]
+        );
+        let path = invalid_path(&Option::<bool>::none(), |b| b[0] = 2);
+        assert_eq!(path.segments(), [PathSegment::Field("tag")]);
+    }
+
+    #[test]
+    fn map_paths_lead_to_the_entry() {
+        let mut map = Map::<u8, bool, 8>::new();
+        for key in 1..=3 {
+            map.insert(key, true).unwrap();
+        }
+        let path = invalid_path(&map, |b| b[8 + 2 * 2 + 1] = 2);
+        assert_eq!(
+            path.segments(),
+            [PathSegment::Index(2), PathSegment::Field("value")]
+        );
+        // the second key is now greater than the third
+        let path = invalid_path(&map, |b| b[8 + 2] = 5);
+        assert_eq!(
+            path.segments(),
+            [PathSegment::Index(2), PathSegment::Field("key")]
+        );
+    }
+
+    #[test]
+    fn set_paths_lead_to_the_item() {
+        let mut set = Set::<u8, 8>::new();
+        for item in 1..=3 {
+            set.insert(item).unwrap();
+        }
+        let path = invalid_path(&set, |b| b[8 + 2] = 2);
+        assert_eq!(path.segments(), [PathSegment::Index(2)]);
+    }
+
+    #[test]
+    fn paths_keep_the_outermost_segments() {
+        let mut path = FieldPath::new();
+        for index in 0..FieldPath::CAPACITY + 1 {
+            path.push_front(PathSegment::Index(index));
+        }
+        assert_eq!(path.to_string(), "[4][3][2][1]");
+    }
+}
@@ -8,25 +8,112 @@ use core::{
     mem::size_of,
     ops::{Deref, DerefMut},
     sync::atomic::{AtomicUsize, Ordering},
+    task::{Poll, Waker},
+    time::Duration,
+};
+use piton::{
+    AsyncDeferRx, AsyncEventTx, AsyncServiceRx, BufW as _, DeferRx, Envelope, Error, EventTx,
+    PendingCall, Reply, ServiceRx, Shutdown, Yule,
+};
+use std::{
+    collections::{binary_heap::PeekMut, BinaryHeap, VecDeque},
+    sync::{Condvar, Mutex, Once, PoisonError},
+    thread::{self, Thread},
+    time::Instant,
 };
-use piton::{BufR as _, BufW as _, Error, ServiceRx, Yule};
 
 pub type Storage<const N: usize = { 4096 * 4 }> = Arc<BufStorage<N>>;
 
 pub struct Tx<const N: usize> {
-    signal: Arc<AtomicUsize>,
+    signal: Arc<Signal>,
     prod: bbqueue::framed::FrameProducer<Storage<N>>,
 }
 
+impl<const N: usize> Tx<N> {
+    fn new(prod: bbqueue::framed::FrameProducer<Storage<N>>, signal: Arc<Signal>) -> Self {
+        signal.senders.fetch_add(1, Ordering::Relaxed);
+        Self { signal, prod }
+    }
+}
+
+impl<const N: usize> Tx<N> {
+    /// Whether the [`Rx`] at the other end of the queue has been dropped, since it holds the only other handle to the signal
+    fn orphaned(&self) -> bool {
+        Arc::strong_count(&self.signal) == 1
+    }
+}
+
+impl<const N: usize> Drop for Tx<N> {
+    fn drop(&mut self) {
+        self.signal.senders.fetch_sub(1, Ordering::Release);
+        // The last sender closes the queue, which the receiver has to find out about
+        self.signal.wake();
+    }
+}
+
 pub struct Rx<const N: usize> {
-    signal: Arc<AtomicUsize>,
+    signal: Arc<Signal>,
     cons: bbqueue::framed::FrameConsumer<Storage<N>>,
 }
 
+/// Shared by both ends of a queue
+#[derive(Default)]
+struct Signal {
+    /// How many frames have been committed but not read
+    pending: AtomicUsize,
+    /// How many [`Tx`]s are left. Once there are none and every frame has been read, the queue is closed.
+    senders: AtomicUsize,
+    /// Woken when the signal is raised or a sender is dropped, if the [`Rx`] is waiting
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Signal {
+    fn raise(&self) {
+        self.pending.fetch_add(1, Ordering::Release);
+        self.wake();
+    }
+
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().expect("waker poisoned").take() {
+            waker.wake();
+        }
+    }
+
+    /// Takes one frame like [`Signal::try_take`], registering `waker` to be woken if there weren't any
+    fn poll_take(&self, waker: &Waker) -> Result<bool, Error> {
+        if self.try_take()? {
+            return Ok(true);
+        }
+        *self.waker.lock().expect("waker poisoned") = Some(waker.clone());
+        // A frame may have been committed before the waker was registered
+        self.try_take()
+    }
+
+    /// Takes one frame, returning `false` if there weren't any and [`Error::Closed`] if there never will be
+    fn try_take(&self) -> Result<bool, Error> {
+        // Senders raise the signal before they drop, so check for them first
+        let closed = self.senders.load(Ordering::Acquire) == 0;
+        if self.pending.load(Ordering::Acquire) != 0 {
+            self.pending.fetch_sub(1, Ordering::Release);
+            return Ok(true);
+        }
+        if closed {
+            Err(Error::Closed)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
 pub struct Server<const N: usize, Arg, Ret> {
     queue: bbqueue::BBBuffer<Storage<N>>,
     rx: Rx<N>,
-    tx: Vec<Tx<N>>,
+    /// The queues replies and events are sent to each client on, shared with [`Publisher`]s and
+    /// with the [`Responder`]s of replies that haven't been sent yet
+    tx: Arc<Mutex<Vec<Arc<Mutex<Tx<N>>>>>>,
+    tail_capacity: usize,
+    /// What the deadlines in request headers are measured from
+    epoch: Instant,
     _phantom: PhantomData<(Arg, Ret)>,
 }
 
@@ -38,43 +125,163 @@ impl<const N: usize, Arg, Ret> Default for Server<N, Arg, Ret> {
             queue,
             rx: Rx {
                 cons,
-                signal: Arc::new(AtomicUsize::new(0)),
+                signal: Arc::default(),
             },
-            tx: vec![],
+            tx: Arc::default(),
+            tail_capacity: 0,
+            epoch: Instant::now(),
             _phantom: PhantomData,
         }
     }
 }
 
 impl<const N: usize, Arg: Yule, Ret: Yule> Server<N, Arg, Ret> {
+    /// Sets how many bytes each message can use for the elements of its variable-length fields.
+    ///
+    /// Buffers are granted with room for the whole trailing region, but only the part of it
+    /// that was written gets committed. Clients created afterwards use the same capacity.
+    pub fn with_tail_capacity(mut self, tail_capacity: usize) -> Self {
+        self.tail_capacity = tail_capacity;
+        self
+    }
+
+    /// Creates a client for this server.
+    ///
+    /// Once every client has been dropped the server's `recv` returns `None`, and once the
+    /// server and its publishers have been dropped its clients' `call`s return [`Error::Closed`].
     pub fn client(&mut self) -> Client<N, Arg, Ret> {
         let reply = bbqueue::BBBuffer::new(Arc::new(BufStorage::new()));
-        let id = self.tx.len();
-        let signal = Arc::new(AtomicUsize::new(0));
-        self.tx.push(Tx {
-            prod: reply.frame_producer(),
-            signal: signal.clone(),
-        });
+        let mut tx = self.tx.lock().expect("a publisher panicked");
+        let id = tx.len();
+        let signal = Arc::<Signal>::default();
+        tx.push(Arc::new(Mutex::new(Tx::new(
+            reply.frame_producer(),
+            signal.clone(),
+        ))));
         Client {
-            tx: Tx {
-                prod: self.queue.frame_producer(),
-                signal: self.rx.signal.clone(),
-            },
+            tx: Tx::new(self.queue.frame_producer(), self.rx.signal.clone()),
             rx: Rx {
                 cons: reply.frame_consumer().expect("consumer already created"),
                 signal,
             },
             id,
+            next_request: 0,
+            outstanding: vec![],
+            stash: vec![],
+            events: VecDeque::new(),
+            tail_capacity: self.tail_capacity,
+            epoch: self.epoch,
             _phantom: PhantomData,
         }
     }
 }
 
-impl<const N: usize, Arg: Yule, Ret: Yule> ServiceRx for Server<N, Arg, Ret> {
+impl<const N: usize, Arg: Envelope, Ret: Reply> Server<N, Arg, Ret> {
+    /// Reads a request that has been taken off the queue, returning it along with its header.
+    ///
+    /// Requests that aren't valid are answered with a [`piton::Status`] here rather than returned,
+    /// so that the client isn't left waiting and the server can carry on. Requests whose deadline
+    /// has passed are dropped without a reply, since their client has stopped waiting for it, and
+    /// so are frames too short to have a header.
+    fn read(
+        &mut self,
+        mut buf: FrameGrantR<Storage<N>>,
+    ) -> Result<Option<(Header, BufR<N, Arg>)>, Error> {
+        buf.auto_release(true);
+        let Ok(header) = Header::read(&buf) else {
+            return Ok(None);
+        };
+        if header.expired(self.epoch) {
+            return Ok(None);
+        }
+        match BufR::new(buf) {
+            Ok(req) => Ok(Some((header, req))),
+            Err(Error::InvalidMsg(e)) => {
+                if let Some((mut resp, tx)) = self.grant_reply(&header)? {
+                    resp.as_mut().set_status(e.into());
+                    send_reply(&tx, resp);
+                }
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Grants the buffer for the reply to the request with `header`, and writes the header into it
+    /// so that the client can tell which request it answers. Returns it along with the queue it's sent on.
+    ///
+    /// Returns `None` if the client has been dropped or its queue is full, since the reply couldn't be
+    /// sent and one client shouldn't stop the server. A client whose queue is full times out instead.
+    #[allow(clippy::type_complexity)]
+    fn grant_reply(
+        &mut self,
+        header: &Header,
+    ) -> Result<Option<(BufW<N, Ret>, Arc<Mutex<Tx<N>>>)>, Error> {
+        let Some(tx) = self.client_tx(header.client)? else {
+            return Ok(None);
+        };
+        let grant = {
+            let Ok(mut client) = tx.lock() else {
+                return Ok(None);
+            };
+            if client.orphaned() {
+                return Ok(None);
+            }
+            let len = size_of::<Ret>() + HEADER_LENGTH + align_of::<Ret>() + self.tail_capacity;
+            match client.prod.grant(len) {
+                Ok(grant) => grant,
+                Err(_) => return Ok(None),
+            }
+        };
+        let mut resp = BufW::<N, Ret> {
+            buf: WriteBuf::Granted(grant),
+            used: 0,
+            _phantom: Default::default(),
+        };
+        resp.buf.bytes_mut().fill(0);
+        header.write(resp.buf.bytes_mut());
+        Ok(Some((resp, tx)))
+    }
+
+    /// Returns the queue replies are sent to `client` on, or `None` if the request named a client that doesn't exist
+    fn client_tx(&self, client: usize) -> Result<Option<Arc<Mutex<Tx<N>>>>, Error> {
+        let tx = self.tx.lock().map_err(|_| Error::TxFail)?;
+        Ok(tx.get(client).cloned())
+    }
+
+    /// Grants the response buffer for a request, returning `None` if the reply can't be sent
+    #[allow(clippy::type_complexity)]
+    fn accept(
+        &mut self,
+        header: Header,
+        req: BufR<N, Arg>,
+    ) -> Result<Option<piton::Recv<BufW<N, Ret>, BufR<N, Arg>, Responder<N, Arg, Ret>>>, Error>
+    {
+        let Some((resp, tx)) = self.grant_reply(&header)? else {
+            return Ok(None);
+        };
+        Ok(Some(piton::Recv {
+            req,
+            resp,
+            responder: Responder {
+                tx,
+                _phantom: PhantomData,
+            },
+            ctx: piton::Context {
+                caller: Some(header.client as u64),
+                request_id: Some(header.request),
+                deadline: header.remaining(self.epoch),
+                header: (),
+            },
+        }))
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> ServiceRx for Server<N, Arg, Ret> {
     type Arg = Arg;
     type Ret = Ret;
 
-    type Responder<'a> = Responder<'a, N, Arg, Ret>;
+    type Responder<'a> = Responder<N, Arg, Ret>;
 
     type BufR<'r> = BufR<N, Self::Arg>;
 
@@ -84,56 +291,227 @@ impl<const N: usize, Arg: Yule, Ret: Yule> ServiceRx for Server<N, Arg, Ret> {
         &mut self,
     ) -> Result<Option<piton::Recv<Self::BufW<'_>, Self::BufR<'_>, Self::Responder<'_>>>, Error>
     {
-        let mut buf = self.rx.recv();
-        let id = usize::from_be_bytes(
-            buf[..{ size_of::<usize>() }]
-                .try_into()
-                .map_err(|_| Error::BufferUnderflow)?,
-        );
-        let tx = &mut self.tx[id];
-        let mut resp = BufW {
-            grant: tx
-                .prod
-                .grant(size_of::<Ret>() + HEADER_LENGTH + align_of::<Ret>())
-                .map_err(|_| Error::BufferUnderflow)?,
-            _phantom: Default::default(),
+        loop {
+            let Some(buf) = closed_as_none(self.rx.recv(None))? else {
+                return Ok(None);
+            };
+            if let Some((header, req)) = self.read(buf)? {
+                // Requests that can't be replied to are dropped rather than stopping the server
+                if let Some(recv) = self.accept(header, req)? {
+                    return Ok(Some(recv));
+                }
+            }
+        }
+    }
+
+    fn recv_until(
+        &mut self,
+        shutdown: &Shutdown,
+    ) -> Result<Option<piton::Recv<Self::BufW<'_>, Self::BufR<'_>, Self::Responder<'_>>>, Error>
+    {
+        loop {
+            let Some(buf) = closed_as_none(self.rx.recv_until(shutdown))?.flatten() else {
+                return Ok(None);
+            };
+            if let Some((header, req)) = self.read(buf)? {
+                if let Some(recv) = self.accept(header, req)? {
+                    return Ok(Some(recv));
+                }
+            }
+        }
+    }
+
+    /// Returns `None` for requests that weren't valid, have expired or can't be replied to, since they have already been dealt with
+    fn try_recv(
+        &mut self,
+    ) -> Result<Option<piton::Recv<Self::BufW<'_>, Self::BufR<'_>, Self::Responder<'_>>>, Error>
+    {
+        let Some(buf) = self.rx.try_recv()? else {
+            return Ok(None);
         };
-        resp.grant.fill(0);
-        buf.auto_release(true);
-        Ok(Some(piton::Recv {
-            req: BufR::new(buf)?,
-            resp,
-            responder: Responder {
-                signal: tx.signal.as_ref(),
-                _phantom: PhantomData,
-            },
-        }))
+        match self.read(buf)? {
+            Some((header, req)) => self.accept(header, req),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> AsyncServiceRx for Server<N, Arg, Ret> {
+    type Arg = Arg;
+    type Ret = Ret;
+
+    type Responder<'a> = Responder<N, Arg, Ret>;
+
+    type BufR<'r> = BufR<N, Self::Arg>;
+
+    type BufW<'r> = BufW<N, Self::Ret>;
+
+    async fn recv(
+        &mut self,
+    ) -> Result<Option<piton::Recv<Self::BufW<'_>, Self::BufR<'_>, Self::Responder<'_>>>, Error>
+    {
+        loop {
+            let Some(buf) = closed_as_none(self.rx.recv_async(None).await)? else {
+                return Ok(None);
+            };
+            if let Some((header, req)) = self.read(buf)? {
+                if let Some(recv) = self.accept(header, req)? {
+                    return Ok(Some(recv));
+                }
+            }
+        }
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> DeferRx for Server<N, Arg, Ret> {
+    /// Copies the reply out of the queue, so that the client's later replies can be granted before it is sent
+    fn defer(
+        resp: Self::BufW<'_>,
+        responder: Self::Responder<'_>,
+    ) -> (Self::BufW<'static>, Self::Responder<'static>) {
+        (resp.stage(), responder)
     }
 }
 
-pub struct Responder<'a, const N: usize, Arg, Ret> {
-    signal: &'a AtomicUsize,
+impl<const N: usize, Arg: Envelope, Ret: Reply> AsyncDeferRx for Server<N, Arg, Ret> {
+    fn defer(
+        resp: Self::BufW<'_>,
+        responder: Self::Responder<'_>,
+    ) -> (Self::BufW<'static>, Self::Responder<'static>) {
+        <Self as DeferRx>::defer(resp, responder)
+    }
+
+    fn send_dropped(resp: Self::BufW<'static>, responder: Self::Responder<'static>) {
+        send_reply(&responder.tx, resp);
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> EventTx for Server<N, Arg, Ret> {
+    type Publisher = Publisher<N, Ret>;
+
+    fn publisher(&self) -> Self::Publisher {
+        Publisher {
+            tx: self.tx.clone(),
+            tail_capacity: self.tail_capacity,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> AsyncEventTx for Server<N, Arg, Ret> {
+    type Publisher = Publisher<N, Ret>;
+
+    fn publisher(&self) -> Self::Publisher {
+        EventTx::publisher(self)
+    }
+}
+
+/// Sends events to every client of a [`Server`], including the ones created after it.
+///
+/// Each event is written once and copied onto the queue of every client that hasn't been dropped.
+pub struct Publisher<const N: usize, Ret> {
+    tx: Arc<Mutex<Vec<Arc<Mutex<Tx<N>>>>>>,
+    tail_capacity: usize,
+    _phantom: PhantomData<Ret>,
+}
+
+impl<const N: usize, Ret: Envelope> piton::BusTx for Publisher<N, Ret> {
+    type Msg = Ret;
+    type BufW<'r> = BufW<N, Ret>;
+
+    fn send(&mut self, msg: Self::BufW<'_>) -> Result<(), Error> {
+        let tx = self.tx.lock().map_err(|_| Error::TxFail)?.clone();
+        msg.broadcast(&tx);
+        Ok(())
+    }
+
+    fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error> {
+        let header = Header {
+            client: 0,
+            request: EVENT,
+            deadline: 0,
+        };
+        let mut bytes = [0; HEADER_LENGTH];
+        header.write(&mut bytes);
+        let frame = vec![0; size_of::<Ret>() + self.tail_capacity];
+        Ok(BufW {
+            buf: WriteBuf::Staged(Stashed::new::<Ret>(&bytes, &frame)),
+            used: 0,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<const N: usize, Ret: Envelope> piton::AsyncBusTx for Publisher<N, Ret> {
+    type Msg = Ret;
+    type BufW<'r> = BufW<N, Ret>;
+
+    async fn send(&mut self, msg: Self::BufW<'_>) -> Result<(), Error> {
+        piton::BusTx::send(self, msg)
+    }
+
+    async fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error> {
+        piton::BusTx::alloc(self)
+    }
+}
+
+pub struct Responder<const N: usize, Arg, Ret> {
+    tx: Arc<Mutex<Tx<N>>>,
     _phantom: PhantomData<(Arg, Ret)>,
 }
 
-impl<'a, const N: usize, Arg: Yule, Ret: Yule> piton::Responder for Responder<'a, N, Arg, Ret> {
+impl<const N: usize, Arg: Envelope, Ret: Reply> piton::Responder for Responder<N, Arg, Ret> {
     type ServerTransport = Server<N, Arg, Ret>;
 
     fn send(self, msg: <Self::ServerTransport as ServiceRx>::BufW<'_>) -> Result<(), Error> {
-        msg.commit();
-        self.signal.fetch_add(1, Ordering::Release);
+        send_reply(&self.tx, msg);
         Ok(())
     }
 }
 
+/// Commits a reply onto the queue to its client.
+///
+/// The reply is dropped if the client has been dropped, or if its queue is too full for a deferred
+/// reply, so that one client can't stop the server. The client times out waiting for it instead.
+fn send_reply<const N: usize, Ret: Envelope>(tx: &Mutex<Tx<N>>, msg: BufW<N, Ret>) {
+    let Ok(mut tx) = tx.lock() else {
+        return;
+    };
+    if !tx.orphaned() && msg.commit(&mut tx.prod).is_ok() {
+        tx.signal.raise();
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> piton::AsyncResponder for Responder<N, Arg, Ret> {
+    type ServerTransport = Server<N, Arg, Ret>;
+
+    async fn send(
+        self,
+        msg: <Self::ServerTransport as AsyncServiceRx>::BufW<'_>,
+    ) -> Result<(), Error> {
+        piton::Responder::send(self, msg)
+    }
+}
+
 pub struct Client<const N: usize, Arg, Ret> {
     id: usize,
+    /// The id the next request is sent with
+    next_request: u64,
+    /// The ids of the requests whose replies haven't been collected, and are still wanted
+    outstanding: Vec<u64>,
+    /// Replies that arrived while waiting for another one, by request id
+    stash: Vec<(u64, Stashed)>,
+    /// Events that arrived while waiting for a reply, oldest first
+    events: VecDeque<Stashed>,
     tx: Tx<N>,
     rx: Rx<N>,
+    tail_capacity: usize,
+    /// The server's epoch, that deadlines are measured from
+    epoch: Instant,
     _phantom: PhantomData<(Arg, Ret)>,
 }
 
-impl<const N: usize, Arg: Yule, Ret: Yule> piton::ServiceTx for Client<N, Arg, Ret> {
+impl<const N: usize, Arg: Envelope, Ret: Reply> piton::ServiceTx for Client<N, Arg, Ret> {
     type Arg = Arg;
     type Ret = Ret;
 
@@ -141,21 +519,263 @@ impl<const N: usize, Arg: Yule, Ret: Yule> piton::ServiceTx for Client<N, Arg, R
 
     type BufR<'r> = BufR<N, Ret>;
 
-    fn call<'r, 'm>(&'r mut self, mut msg: Self::BufW<'m>) -> Result<Self::BufR<'r>, Error> {
-        msg.grant[0..{ size_of::<usize>() }].copy_from_slice(&self.id.to_be_bytes());
-        msg.commit();
-        self.tx.signal.fetch_add(1, Ordering::Release);
-        let mut resp = self.rx.recv();
-        resp.auto_release(true);
-        BufR::new(resp)
+    fn call<'r, 'm>(&'r mut self, msg: Self::BufW<'m>) -> Result<Self::BufR<'r>, Error> {
+        self.call_until(msg, None)
+    }
+
+    fn call_timeout<'r, 'm>(
+        &'r mut self,
+        msg: Self::BufW<'m>,
+        timeout: Duration,
+    ) -> Result<Self::BufR<'r>, Error> {
+        self.call_until(msg, Instant::now().checked_add(timeout))
+    }
+
+    fn start_call(&mut self, msg: Self::BufW<'_>) -> Result<PendingCall, Error> {
+        Ok(PendingCall::new(self.send(msg, None)?))
+    }
+
+    fn finish_call(&mut self, call: PendingCall) -> Result<Self::BufR<'_>, Error> {
+        self.check_outstanding(&call)?;
+        self.wait_reply(call.id(), None)
     }
 
     fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error> {
+        self.grant()
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> piton::AsyncServiceTx for Client<N, Arg, Ret> {
+    type Arg = Arg;
+    type Ret = Ret;
+
+    type BufW<'r> = BufW<N, Arg>;
+
+    type BufR<'r> = BufR<N, Ret>;
+
+    async fn call<'r, 'm>(&'r mut self, msg: Self::BufW<'m>) -> Result<Self::BufR<'r>, Error> {
+        self.call_until_async(msg, None).await
+    }
+
+    async fn call_timeout<'r, 'm>(
+        &'r mut self,
+        msg: Self::BufW<'m>,
+        timeout: Duration,
+    ) -> Result<Self::BufR<'r>, Error> {
+        self.call_until_async(msg, Instant::now().checked_add(timeout))
+            .await
+    }
+
+    async fn start_call(&mut self, msg: Self::BufW<'_>) -> Result<PendingCall, Error> {
+        piton::ServiceTx::start_call(self, msg)
+    }
+
+    async fn finish_call(&mut self, call: PendingCall) -> Result<Self::BufR<'_>, Error> {
+        self.check_outstanding(&call)?;
+        self.wait_reply_async(call.id(), None).await
+    }
+
+    async fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error> {
+        self.grant()
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> piton::EventRx for Client<N, Arg, Ret> {
+    fn recv_event(&mut self) -> Result<Option<Self::BufR<'_>>, Error> {
+        if let Some(event) = self.events.pop_front() {
+            return BufR::from_stashed(event).map(Some);
+        }
+        loop {
+            let Some(frame) = closed_as_none(self.rx.recv(None))? else {
+                return Ok(None);
+            };
+            if let Some(event) = self.sort_event(frame) {
+                return event.map(Some);
+            }
+        }
+    }
+
+    fn try_recv_event(&mut self) -> Result<Option<Self::BufR<'_>>, Error> {
+        if let Some(event) = self.events.pop_front() {
+            return BufR::from_stashed(event).map(Some);
+        }
+        while let Some(frame) = self.rx.try_recv()? {
+            if let Some(event) = self.sort_event(frame) {
+                return event.map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> piton::AsyncEventRx for Client<N, Arg, Ret> {
+    async fn recv_event(&mut self) -> Result<Option<Self::BufR<'_>>, Error> {
+        if let Some(event) = self.events.pop_front() {
+            return BufR::from_stashed(event).map(Some);
+        }
+        loop {
+            let Some(frame) = closed_as_none(self.rx.recv_async(None).await)? else {
+                return Ok(None);
+            };
+            if let Some(event) = self.sort_event(frame) {
+                return event.map(Some);
+            }
+        }
+    }
+}
+
+impl<const N: usize, Arg: Envelope, Ret: Reply> Client<N, Arg, Ret> {
+    /// Sends a request and waits for its reply, until `deadline` if there is one
+    fn call_until(
+        &mut self,
+        msg: BufW<N, Arg>,
+        deadline: Option<Instant>,
+    ) -> Result<BufR<N, Ret>, Error> {
+        let request = self.send(msg, deadline)?;
+        self.wait_reply(request, deadline)
+    }
+
+    /// The async version of [`Client::call_until`]
+    async fn call_until_async(
+        &mut self,
+        msg: BufW<N, Arg>,
+        deadline: Option<Instant>,
+    ) -> Result<BufR<N, Ret>, Error> {
+        let request = self.send(msg, deadline)?;
+        self.wait_reply_async(request, deadline).await
+    }
+
+    /// Waits for the reply to `request`, until `deadline` if there is one. Either way the request stops
+    /// being outstanding, so a reply that turns up after a timeout is discarded.
+    fn wait_reply(
+        &mut self,
+        request: u64,
+        deadline: Option<Instant>,
+    ) -> Result<BufR<N, Ret>, Error> {
+        let res = match self.take_stashed(request) {
+            Some(frame) => BufR::from_stashed(frame),
+            None => loop {
+                match self.rx.recv(deadline) {
+                    Ok(resp) => {
+                        if let Some(res) = self.sort_reply(request, resp) {
+                            break res;
+                        }
+                    }
+                    Err(e) => break Err(e),
+                }
+            },
+        };
+        self.outstanding.retain(|&id| id != request);
+        res
+    }
+
+    /// The async version of [`Client::wait_reply`]
+    async fn wait_reply_async(
+        &mut self,
+        request: u64,
+        deadline: Option<Instant>,
+    ) -> Result<BufR<N, Ret>, Error> {
+        let res = match self.take_stashed(request) {
+            Some(frame) => BufR::from_stashed(frame),
+            None => loop {
+                match self.rx.recv_async(deadline).await {
+                    Ok(resp) => {
+                        if let Some(res) = self.sort_reply(request, resp) {
+                            break res;
+                        }
+                    }
+                    Err(e) => break Err(e),
+                }
+            },
+        };
+        self.outstanding.retain(|&id| id != request);
+        res
+    }
+
+    /// Returns [`Error::RxFail`] if `call` wasn't started on this client
+    fn check_outstanding(&self, call: &PendingCall) -> Result<(), Error> {
+        if self.outstanding.contains(&call.id()) {
+            Ok(())
+        } else {
+            Err(Error::RxFail)
+        }
+    }
+
+    fn take_stashed(&mut self, request: u64) -> Option<Stashed> {
+        let index = self.stash.iter().position(|(id, _)| *id == request)?;
+        Some(self.stash.swap_remove(index).1)
+    }
+
+    /// Sends a request that expires at `deadline`, returning its id
+    fn send(&mut self, mut msg: BufW<N, Arg>, deadline: Option<Instant>) -> Result<u64, Error> {
+        let header = Header {
+            client: self.id,
+            request: self.next_request,
+            deadline: deadline.map_or(0, |d| Header::deadline_since(self.epoch, d)),
+        };
+        header.write(msg.buf.bytes_mut());
+        self.next_request = self.next_request.wrapping_add(1);
+        self.outstanding.push(header.request);
+        msg.commit(&mut self.tx.prod)?;
+        self.tx.signal.raise();
+        Ok(header.request)
+    }
+
+    /// Returns the reply in `resp` if it answers `request`. If it answers another outstanding request
+    /// it is stashed until that one is waited for, and otherwise it is the late reply to a call that
+    /// timed out, and is discarded.
+    fn sort_reply(
+        &mut self,
+        request: u64,
+        mut resp: FrameGrantR<Storage<N>>,
+    ) -> Option<Result<BufR<N, Ret>, Error>> {
+        resp.auto_release(true);
+        let id = match Header::read(&resp) {
+            Ok(header) => header.request,
+            Err(e) => return Some(Err(e)),
+        };
+        if id == request {
+            return Some(BufR::new(resp));
+        }
+        if id == EVENT {
+            self.events
+                .push_back(Stashed::new::<Ret>(&resp, frame_of::<Ret>(&resp)));
+        } else if self.outstanding.contains(&id) {
+            self.stash
+                .push((id, Stashed::new::<Ret>(&resp, frame_of::<Ret>(&resp))));
+        }
+        None
+    }
+
+    /// Returns the event in `frame`, or stashes it if it is the reply to an outstanding request
+    fn sort_event(
+        &mut self,
+        mut frame: FrameGrantR<Storage<N>>,
+    ) -> Option<Result<BufR<N, Ret>, Error>> {
+        frame.auto_release(true);
+        let id = match Header::read(&frame) {
+            Ok(header) => header.request,
+            Err(e) => return Some(Err(e)),
+        };
+        if id == EVENT {
+            return Some(BufR::new(frame));
+        }
+        if self.outstanding.contains(&id) {
+            self.stash
+                .push((id, Stashed::new::<Ret>(&frame, frame_of::<Ret>(&frame))));
+        }
+        None
+    }
+
+    fn grant(&mut self) -> Result<BufW<N, Arg>, Error> {
         self.tx
             .prod
-            .grant(size_of::<Arg>() + HEADER_LENGTH + align_of::<Arg>())
+            .grant(size_of::<Arg>() + HEADER_LENGTH + align_of::<Arg>() + self.tail_capacity)
             .map_err(|_| Error::BufferOverflow)
-            .map(|g| unsafe { BufW::new(g) })
+            .map(|mut g| {
+                g.fill(0);
+                unsafe { BufW::new(g) }
+            })
     }
 }
 
@@ -164,13 +784,13 @@ struct BusTx<const N: usize, Msg> {
     _phantom: PhantomData<Msg>,
 }
 
-impl<const N: usize, Msg: Yule> piton::BusTx for BusTx<N, Msg> {
+impl<const N: usize, Msg: Envelope> piton::BusTx for BusTx<N, Msg> {
     type Msg = Msg;
     type BufW<'r> = BufW<N, Self::Msg>;
 
     fn send(&mut self, msg: Self::BufW<'_>) -> Result<(), Error> {
-        msg.commit();
-        self.tx.signal.fetch_add(1, Ordering::Release);
+        msg.commit(&mut self.tx.prod)?;
+        self.tx.signal.raise();
         Ok(())
     }
 
@@ -179,7 +799,23 @@ impl<const N: usize, Msg: Yule> piton::BusTx for BusTx<N, Msg> {
             .prod
             .grant(size_of::<Msg>() + align_of::<Msg>() + HEADER_LENGTH)
             .map_err(|_| Error::BufferOverflow)
-            .map(|g| unsafe { BufW::new(g) })
+            .map(|mut g| {
+                g.fill(0);
+                unsafe { BufW::new(g) }
+            })
+    }
+}
+
+impl<const N: usize, Msg: Envelope> piton::AsyncBusTx for BusTx<N, Msg> {
+    type Msg = Msg;
+    type BufW<'r> = BufW<N, Self::Msg>;
+
+    async fn send(&mut self, msg: Self::BufW<'_>) -> Result<(), Error> {
+        piton::BusTx::send(self, msg)
+    }
+
+    async fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error> {
+        piton::BusTx::alloc(self)
     }
 }
 
@@ -188,49 +824,174 @@ struct BusRx<const N: usize, Msg> {
     _phantom: PhantomData<Msg>,
 }
 
-impl<const N: usize, Msg: Yule> piton::BusRx for BusRx<N, Msg> {
+impl<const N: usize, Msg: Envelope> piton::BusRx for BusRx<N, Msg> {
     type Msg = Msg;
     type BufR<'r> = BufR<N, Msg>;
 
     fn recv(&mut self) -> Result<Option<Self::BufR<'_>>, Error> {
-        let mut buf = self.rx.recv();
+        let Some(mut buf) = closed_as_none(self.rx.recv(None))? else {
+            return Ok(None);
+        };
+        buf.auto_release(true);
+        Ok(Some(BufR::new(buf)?))
+    }
+
+    fn try_recv(&mut self) -> Result<Option<Self::BufR<'_>>, Error> {
+        let Some(mut buf) = self.rx.try_recv()? else {
+            return Ok(None);
+        };
+        buf.auto_release(true);
+        Ok(Some(BufR::new(buf)?))
+    }
+
+    fn recv_until(&mut self, shutdown: &Shutdown) -> Result<Option<Self::BufR<'_>>, Error> {
+        let Some(mut buf) = closed_as_none(self.rx.recv_until(shutdown))?.flatten() else {
+            return Ok(None);
+        };
+        buf.auto_release(true);
+        Ok(Some(BufR::new(buf)?))
+    }
+}
+
+impl<const N: usize, Msg: Envelope> piton::AsyncBusRx for BusRx<N, Msg> {
+    type Msg = Msg;
+    type BufR<'r> = BufR<N, Msg>;
+
+    async fn recv(&mut self) -> Result<Option<Self::BufR<'_>>, Error> {
+        let Some(mut buf) = closed_as_none(self.rx.recv_async(None).await)? else {
+            return Ok(None);
+        };
         buf.auto_release(true);
         Ok(Some(BufR::new(buf)?))
     }
 }
 
-const HEADER_LENGTH: usize = size_of::<usize>();
+const HEADER_LENGTH: usize = size_of::<usize>() + 2 * size_of::<u64>();
+
+/// The request id events are sent with, in place of the id of a request they answer.
+/// Request ids count up from 0, so no request gets it.
+const EVENT: u64 = u64::MAX;
+
+/// Written in front of every request, so that the server knows who to reply to, and in front of
+/// every reply, so that the client knows which request it answers
+#[derive(Clone, Copy)]
+struct Header {
+    /// The id of the client that sent the request, which is its index in [`Server::tx`]. Unused by events
+    client: usize,
+    /// Counts up with each request the client sends
+    request: u64,
+    /// Nanoseconds from the server's epoch until the request expires, or 0 if it never does
+    deadline: u64,
+}
+
+impl Header {
+    fn read(buf: &[u8]) -> Result<Self, Error> {
+        let buf = buf.get(..HEADER_LENGTH).ok_or(Error::BufferUnderflow)?;
+        let (client, rest) = buf.split_at(size_of::<usize>());
+        let (request, deadline) = rest.split_at(size_of::<u64>());
+        Ok(Header {
+            client: usize::from_be_bytes(client.try_into().map_err(|_| Error::BufferUnderflow)?),
+            request: u64::from_be_bytes(request.try_into().map_err(|_| Error::BufferUnderflow)?),
+            deadline: u64::from_be_bytes(deadline.try_into().map_err(|_| Error::BufferUnderflow)?),
+        })
+    }
+
+    fn write(&self, buf: &mut [u8]) {
+        let (client, rest) = buf[..HEADER_LENGTH].split_at_mut(size_of::<usize>());
+        let (request, deadline) = rest.split_at_mut(size_of::<u64>());
+        client.copy_from_slice(&self.client.to_be_bytes());
+        request.copy_from_slice(&self.request.to_be_bytes());
+        deadline.copy_from_slice(&self.deadline.to_be_bytes());
+    }
+
+    /// Encodes `deadline` as the nanoseconds since `epoch`, never 0 so that it can't be mistaken for no deadline
+    fn deadline_since(epoch: Instant, deadline: Instant) -> u64 {
+        let nanos = deadline.saturating_duration_since(epoch).as_nanos();
+        nanos.clamp(1, u64::MAX as u128) as u64
+    }
+
+    fn expired(&self, epoch: Instant) -> bool {
+        self.deadline != 0 && epoch.elapsed().as_nanos() >= self.deadline as u128
+    }
+
+    /// Returns how long is left until the request expires, or `None` if it never does
+    fn remaining(&self, epoch: Instant) -> Option<Duration> {
+        (self.deadline != 0)
+            .then(|| Duration::from_nanos(self.deadline).saturating_sub(epoch.elapsed()))
+    }
+}
 
 pub struct BufW<const N: usize, T> {
-    grant: bbqueue::framed::FrameGrantW<Storage<N>>,
+    buf: WriteBuf<N>,
+    /// How much of the trailing region has been written
+    used: usize,
     _phantom: PhantomData<T>,
 }
 
+/// Where a [`BufW`] is written
+enum WriteBuf<const N: usize> {
+    /// In the queue
+    Granted(bbqueue::framed::FrameGrantW<Storage<N>>),
+    /// Copied out of the queue, and granted again when it is sent
+    Staged(Stashed),
+}
+
+impl<const N: usize> WriteBuf<N> {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            WriteBuf::Granted(grant) => grant,
+            WriteBuf::Staged(staged) => staged.bytes(),
+        }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        match self {
+            WriteBuf::Granted(grant) => grant,
+            WriteBuf::Staged(staged) => staged.bytes_mut(),
+        }
+    }
+}
+
 impl<const N: usize, T> BufW<N, T> {
     /// Safety: Ensure grant is zeroed before calling this method
     unsafe fn new(grant: bbqueue::framed::FrameGrantW<Storage<N>>) -> Self {
-        //assert!(grant.len() >= size_of::<T>() + HEADER_LENGTH + align_of::<T>());
         Self {
-            grant,
+            buf: WriteBuf::Granted(grant),
+            used: 0,
             _phantom: Default::default(),
         }
     }
+
+    /// Copies the buffer out of the queue and releases its grant, so that another can be granted before it is sent
+    fn stage(self) -> Self {
+        let buf = match self.buf {
+            WriteBuf::Granted(grant) => {
+                WriteBuf::Staged(Stashed::new::<T>(&grant, frame_of::<T>(&grant)))
+            }
+            staged => staged,
+        };
+        Self {
+            buf,
+            used: self.used,
+            _phantom: PhantomData,
+        }
+    }
 }
 
 impl<'a, const N: usize, T: piton::Yule> piton::BufW<'a, T> for BufW<N, T> {
     fn as_mut(&mut self) -> &mut T {
-        let addr = self.grant.as_ptr();
-        let offset = addr.align_offset(align_of::<T>()) + HEADER_LENGTH;
-        // Safety: BufW's contents are validated on creation
-        unsafe { T::from_mut_slice_unchecked(&mut self.grant.deref_mut()[offset..]) }
+        let offset = self.offset();
+        // Safety: BufW's contents start zeroed and are only written through `T`. They aren't validated, since
+        // a `nonzero` field is zero until it's set.
+        unsafe { &mut *(self.buf.bytes_mut()[offset..].as_mut_ptr() as *mut T) }
     }
-}
-impl<'a, const N: usize, T: piton::Yule> piton::BufR<'a, T> for BufW<N, T> {
-    fn as_ref(&self) -> &T {
-        let addr = self.grant.as_ptr();
-        let offset = addr.align_offset(align_of::<T>()) + HEADER_LENGTH;
-        // Safety: BufW's contents are validated on creation
-        unsafe { T::from_slice_unchecked(&self.grant.deref()[offset..]) }
+
+    fn split_tail(&mut self) -> (&mut T, piton::types::TailWriter<'_>) {
+        let offset = self.offset();
+        let (msg, tail) = self.buf.bytes_mut().split_at_mut(offset + size_of::<T>());
+        // Safety: see `as_mut`, and `msg` is exactly as long as `T`
+        let msg = unsafe { &mut *(msg[offset..].as_mut_ptr() as *mut T) };
+        (msg, piton::types::TailWriter::new(tail, &mut self.used))
     }
 }
 
@@ -243,61 +1004,477 @@ impl<const N: usize, T: piton::Yule> Deref for BufW<N, T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        self.as_ref()
+        // Safety: see `as_mut`
+        unsafe { &*(self.buf.bytes()[self.offset()..].as_ptr() as *const T) }
     }
 }
 
-impl<const N: usize, T: piton::Yule> Deref for BufR<N, T> {
-    type Target = T;
+impl<'a, const N: usize, T: piton::Envelope> piton::BufR<'a, T> for BufR<N, T> {
+    fn frame(&self) -> &[u8] {
+        match &self.frame {
+            Frame::Granted(grant) => frame_of::<T>(grant),
+            Frame::Stashed(stashed) => frame_of::<T>(stashed.bytes()),
+        }
+    }
 
-    fn deref(&self) -> &T {
-        self.as_ref()
+    fn view(&self) -> T::Ref<'_> {
+        // Safety: BufR's frame is validated on creation
+        unsafe { T::view_unchecked(self.frame()) }
     }
 }
-impl<'a, const N: usize, T: piton::Yule> piton::BufR<'a, T> for BufR<N, T> {
-    fn as_ref(&self) -> &T {
-        let addr = self.grant.as_ptr();
-        let offset = addr.align_offset(align_of::<T>()) + HEADER_LENGTH;
-        // Safety: BufW's contents are validated on creation
-        unsafe { T::from_slice_unchecked(&self.grant.deref()[offset..]) }
+
+impl<const N: usize, T> BufW<N, T> {
+    fn offset(&self) -> usize {
+        frame_pad::<T>(self.buf.bytes()) + HEADER_LENGTH
     }
 }
 
-impl<const N: usize, T> BufW<N, T> {
-    fn commit(self) {
-        self.grant
-            .commit(size_of::<T>() + align_of::<T>() + HEADER_LENGTH)
+impl<const N: usize, T: piton::Envelope> BufW<N, T> {
+    /// Commits the header, the variant in use and the part of the trailing region that was written.
+    ///
+    /// The trailing region is written after the whole envelope, so it is moved down to where
+    /// [`piton::Envelope::tail_start`] says it starts first. A staged buffer is copied into a grant from `prod`.
+    fn commit(
+        mut self,
+        prod: &mut bbqueue::framed::FrameProducer<Storage<N>>,
+    ) -> Result<(), Error> {
+        let end = self.compact();
+        match self.buf {
+            WriteBuf::Granted(grant) => grant.commit(end),
+            WriteBuf::Staged(staged) => commit_staged::<N, T>(&staged.bytes()[..end], prod)?,
+        }
+        Ok(())
+    }
+
+    /// Commits a copy of the buffer onto each of the queues in `tx`, skipping the ones whose client
+    /// has been dropped. Clients whose queue is full miss the event rather than stopping the others getting it.
+    fn broadcast(self, tx: &[Arc<Mutex<Tx<N>>>]) {
+        let mut staged = self.stage();
+        let end = staged.compact();
+        for tx in tx {
+            let Ok(mut tx) = tx.lock() else {
+                continue;
+            };
+            if tx.orphaned() {
+                continue;
+            }
+            if commit_staged::<N, T>(&staged.buf.bytes()[..end], &mut tx.prod).is_ok() {
+                tx.signal.raise();
+            }
+        }
+    }
+
+    /// Moves the part of the trailing region that was written down to where it starts, returning where the frame ends
+    fn compact(&mut self) -> usize {
+        let offset = self.offset();
+        let frame_len = offset + self.deref().frame_len();
+        if self.used == 0 {
+            return frame_len;
+        }
+        let tail = offset + size_of::<T>();
+        let tail_start = offset + T::tail_start(frame_len - offset);
+        let used = self.used;
+        self.buf
+            .bytes_mut()
+            .copy_within(tail..tail + used, tail_start);
+        tail_start + used
     }
 }
+
+/// Copies the header and frame of a staged buffer into a grant from `prod` and commits it.
+/// Staged buffers have no padding before the frame, but the new grant might need some.
+fn commit_staged<const N: usize, T>(
+    bytes: &[u8],
+    prod: &mut bbqueue::framed::FrameProducer<Storage<N>>,
+) -> Result<(), Error> {
+    let end = bytes.len();
+    let mut grant = prod
+        .grant(align_of::<T>() + end)
+        .map_err(|_| Error::BufferOverflow)?;
+    let pad = frame_pad::<T>(&grant);
+    grant[..HEADER_LENGTH].copy_from_slice(&bytes[..HEADER_LENGTH]);
+    grant[pad + HEADER_LENGTH..pad + end].copy_from_slice(&bytes[HEADER_LENGTH..]);
+    grant.commit(pad + end);
+    Ok(())
+}
 pub struct BufR<const N: usize, T> {
-    grant: bbqueue::framed::FrameGrantR<Storage<N>>,
+    frame: Frame<N>,
     _phantom: PhantomData<T>,
 }
 
-impl<const N: usize, T: Yule> BufR<N, T> {
-    fn new(grant: bbqueue::framed::FrameGrantR<Storage<N>>) -> Result<Self, Error> {
-        let addr = grant.as_ptr();
-        let offset = addr.align_offset(align_of::<T>()) + HEADER_LENGTH;
-        if !T::validate(&grant[offset..]) {
-            return Err(Error::InvalidMsg);
-        }
+/// Where a [`BufR`]'s frame is
+enum Frame<const N: usize> {
+    /// Still in the queue
+    Granted(FrameGrantR<Storage<N>>),
+    /// Copied out of the queue, so that it could be read past
+    Stashed(Stashed),
+}
+
+impl<const N: usize, T: piton::Envelope> BufR<N, T> {
+    fn new(grant: FrameGrantR<Storage<N>>) -> Result<Self, Error> {
+        T::check_frame(frame_of::<T>(&grant)).map_err(Error::InvalidMsg)?;
         Ok(BufR {
-            grant,
+            frame: Frame::Granted(grant),
             _phantom: Default::default(),
         })
     }
+
+    fn from_stashed(stashed: Stashed) -> Result<Self, Error> {
+        T::check_frame(frame_of::<T>(stashed.bytes())).map_err(Error::InvalidMsg)?;
+        Ok(BufR {
+            frame: Frame::Stashed(stashed),
+            _phantom: Default::default(),
+        })
+    }
+}
+
+/// Returns the frame in `grant`, which starts after the header, aligned for `T`
+fn frame_of<T>(grant: &[u8]) -> &[u8] {
+    &grant[frame_pad::<T>(grant) + HEADER_LENGTH..]
+}
+
+/// Returns the padding between the header at the start of `buf` and the frame. It's the frame
+/// that has to be aligned for `T` rather than `buf`, since the header needn't be a multiple of `T`'s alignment.
+fn frame_pad<T>(buf: &[u8]) -> usize {
+    buf.as_ptr()
+        .wrapping_add(HEADER_LENGTH)
+        .align_offset(align_of::<T>())
+}
+
+/// A header and frame copied out of the queue, laid out like a grant that needed no padding
+struct Stashed {
+    buf: Vec<u8>,
+    /// Where the copy starts in `buf`, so that the frame after the header is aligned
+    start: usize,
+}
+
+impl Stashed {
+    /// Copies `header` and `frame` into a buffer, with the frame where [`frame_of`] looks for it
+    fn new<T>(header: &[u8], frame: &[u8]) -> Self {
+        let len = HEADER_LENGTH + frame.len();
+        let mut buf = vec![0; len + align_of::<T>()];
+        let start = frame_pad::<T>(&buf);
+        buf.truncate(start + len);
+        let (to_header, to_frame) = buf[start..].split_at_mut(HEADER_LENGTH);
+        to_header.copy_from_slice(&header[..HEADER_LENGTH]);
+        to_frame.copy_from_slice(frame);
+        Self { buf, start }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.buf[self.start..]
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.buf[self.start..]
+    }
+}
+
+/// Parks the thread until the signal is raised, or returns [`Error::Timeout`] once `deadline` has passed
+fn wait(signal: &Signal, deadline: Option<Instant>) -> Result<(), Error> {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    while !signal.poll_take(&waker)? {
+        match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(Error::Timeout);
+                }
+                thread::park_timeout(deadline - now);
+            }
+            None => thread::park(),
+        }
+    }
+    Ok(())
+}
+
+/// Waits for the signal like [`wait`] without a deadline, returning `false` once `shutdown` is called instead.
+/// The shutdown wakes the same waker as the signal, so nothing is polled.
+fn wait_until(signal: &Signal, shutdown: &Shutdown) -> Result<bool, Error> {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let _registered = shutdown.register(&waker);
+    loop {
+        if shutdown.is_shutdown() {
+            return Ok(false);
+        }
+        if signal.poll_take(&waker)? {
+            return Ok(true);
+        }
+        thread::park();
+    }
+}
+
+/// Wakes a thread parked in [`wait`] or [`wait_until`]
+struct Unpark(Thread);
+
+impl std::task::Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// The async version of [`wait`]. The task is woken when the signal is raised, and by [`TIMER`]
+/// once `deadline` has passed, since there's no executor-independent timer to use instead.
+async fn wait_async(signal: &Signal, deadline: Option<Instant>) -> Result<(), Error> {
+    let mut timer_started = false;
+    core::future::poll_fn(|cx| match signal.poll_take(cx.waker()) {
+        Ok(false) => match deadline {
+            Some(deadline) if Instant::now() >= deadline => Poll::Ready(Err(Error::Timeout)),
+            Some(deadline) => {
+                if !timer_started {
+                    timer_started = true;
+                    TIMER.wake_at(deadline, cx.waker().clone());
+                }
+                Poll::Pending
+            }
+            None => Poll::Pending,
+        },
+        res => Poll::Ready(res.map(|_| ())),
+    })
+    .await
+}
+
+/// Wakes the tasks in [`wait_async`] once their deadlines pass. One thread serves every deadline,
+/// started the first time one is set.
+static TIMER: Timer = Timer {
+    deadlines: Mutex::new(BinaryHeap::new()),
+    changed: Condvar::new(),
+    started: Once::new(),
+};
+
+struct Timer {
+    deadlines: Mutex<BinaryHeap<Deadline>>,
+    /// Notified when a deadline is added, which may be sooner than the one the thread is waiting for
+    changed: Condvar,
+    started: Once,
+}
+
+impl Timer {
+    /// Wakes `waker` once `at` has passed. A task that finishes waiting sooner is still woken then,
+    /// which it has to put up with like any other spurious wake.
+    fn wake_at(&'static self, at: Instant, waker: Waker) {
+        self.started.call_once(|| {
+            thread::spawn(|| self.run());
+        });
+        self.lock().push(Deadline { at, waker });
+        self.changed.notify_one();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BinaryHeap<Deadline>> {
+        // The heap is left consistent by every change to it, so it's still usable after a panic
+        self.deadlines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn run(&self) {
+        let mut deadlines = self.lock();
+        loop {
+            let now = Instant::now();
+            let mut expired = Vec::new();
+            while let Some(deadline) = deadlines.peek_mut().filter(|d| d.at <= now) {
+                expired.push(PeekMut::pop(deadline).waker);
+            }
+            if !expired.is_empty() {
+                // Wakers can run anything, so they're woken without the lock held
+                drop(deadlines);
+                expired.into_iter().for_each(Waker::wake);
+                deadlines = self.lock();
+                continue;
+            }
+            deadlines = match deadlines.peek() {
+                Some(next) => {
+                    let timeout = next.at - now;
+                    let waited = self.changed.wait_timeout(deadlines, timeout);
+                    waited.unwrap_or_else(PoisonError::into_inner).0
+                }
+                None => self
+                    .changed
+                    .wait(deadlines)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+    }
 }
 
-fn spin_wait(signal: &AtomicUsize) {
-    while signal.load(Ordering::Acquire) == 0 {
-        core::hint::spin_loop();
+/// A waker to wake at `at`, ordered so that the [`BinaryHeap`] of them puts the soonest first
+struct Deadline {
+    at: Instant,
+    waker: Waker,
+}
+
+impl PartialEq for Deadline {
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at
+    }
+}
+
+impl Eq for Deadline {}
+
+impl PartialOrd for Deadline {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Deadline {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        other.at.cmp(&self.at)
+    }
+}
+
+/// Turns [`Error::Closed`] into `None`, for the `recv`s that return `None` once the queue is closed
+fn closed_as_none<T>(res: Result<T, Error>) -> Result<Option<T>, Error> {
+    match res {
+        Ok(t) => Ok(Some(t)),
+        Err(Error::Closed) => Ok(None),
+        Err(e) => Err(e),
     }
-    signal.fetch_sub(1, Ordering::Release);
 }
 
 impl<const N: usize> Rx<N> {
-    fn recv(&mut self) -> FrameGrantR<Storage<N>> {
-        spin_wait(self.signal.as_ref());
-        self.cons.read().expect("race condition")
+    /// Waits for a frame, until `deadline` if there is one.
+    /// Returns [`Error::RxFail`] if the signal was raised without a frame to read
+    fn recv(&mut self, deadline: Option<Instant>) -> Result<FrameGrantR<Storage<N>>, Error> {
+        wait(&self.signal, deadline)?;
+        self.cons.read().ok_or(Error::RxFail)
+    }
+
+    /// Waits for a frame, returning `None` once `shutdown` is called
+    fn recv_until(
+        &mut self,
+        shutdown: &Shutdown,
+    ) -> Result<Option<FrameGrantR<Storage<N>>>, Error> {
+        if !wait_until(&self.signal, shutdown)? {
+            return Ok(None);
+        }
+        self.cons.read().map(Some).ok_or(Error::RxFail)
+    }
+
+    fn try_recv(&mut self) -> Result<Option<FrameGrantR<Storage<N>>>, Error> {
+        if !self.signal.try_take()? {
+            return Ok(None);
+        }
+        self.cons.read().map(Some).ok_or(Error::RxFail)
+    }
+
+    async fn recv_async(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> Result<FrameGrantR<Storage<N>>, Error> {
+        wait_async(&self.signal, deadline).await?;
+        self.cons.read().ok_or(Error::RxFail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{future::Future, pin::pin, task::Context};
+
+    /// Polls `fut` on this thread, parking between polls, and returns its output and how many polls it took
+    fn block_on<F: Future>(fut: F) -> (F::Output, usize) {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = pin!(fut);
+        let mut polls = 0;
+        loop {
+            polls += 1;
+            if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+                return (out, polls);
+            }
+            thread::park();
+        }
+    }
+
+    fn open_signal() -> Arc<Signal> {
+        let signal = Arc::<Signal>::default();
+        signal.senders.fetch_add(1, Ordering::Relaxed);
+        signal
+    }
+
+    fn raise_after(signal: &Arc<Signal>, delay: Duration) -> thread::JoinHandle<()> {
+        let signal = signal.clone();
+        thread::spawn(move || {
+            thread::sleep(delay);
+            signal.raise();
+        })
+    }
+
+    #[test]
+    fn async_wait_is_woken_by_raise() {
+        let signal = open_signal();
+        let raiser = raise_after(&signal, Duration::from_millis(50));
+        let (res, polls) = block_on(wait_async(&signal, None));
+        raiser.join().unwrap();
+        assert!(res.is_ok());
+        // once before the raise and once after, give or take a spurious unpark
+        assert!(polls <= 3, "polled {polls} times");
+    }
+
+    #[test]
+    fn async_wait_times_out() {
+        let signal = open_signal();
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let (res, polls) = block_on(wait_async(&signal, Some(deadline)));
+        assert!(matches!(res, Err(Error::Timeout)));
+        assert!(Instant::now() >= deadline);
+        assert!(polls <= 3, "polled {polls} times");
+    }
+
+    #[test]
+    fn timer_wakes_sooner_deadlines_set_later() {
+        let start = Instant::now();
+        let late = thread::spawn(move || {
+            let signal = open_signal();
+            block_on(wait_async(
+                &signal,
+                Some(start + Duration::from_millis(300)),
+            ))
+            .0
+        });
+        thread::sleep(Duration::from_millis(20));
+        let signal = open_signal();
+        let (res, _) = block_on(wait_async(&signal, Some(start + Duration::from_millis(60))));
+        assert!(matches!(res, Err(Error::Timeout)));
+        assert!(start.elapsed() < Duration::from_millis(300));
+        assert!(matches!(late.join().unwrap(), Err(Error::Timeout)));
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn wait_is_woken_by_raise() {
+        let signal = open_signal();
+        let raiser = raise_after(&signal, Duration::from_millis(50));
+        assert!(wait(&signal, None).is_ok());
+        raiser.join().unwrap();
+        let deadline = Instant::now() + Duration::from_millis(20);
+        assert!(matches!(wait(&signal, Some(deadline)), Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn wait_is_woken_when_closed() {
+        let signal = open_signal();
+        let closer = {
+            let signal = signal.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                signal.senders.fetch_sub(1, Ordering::Release);
+                signal.wake();
+            })
+        };
+        assert!(matches!(wait(&signal, None), Err(Error::Closed)));
+        closer.join().unwrap();
+    }
+
+    #[test]
+    fn staged_frames_that_dont_fit_overflow() {
+        let queue = bbqueue::BBBuffer::new(Arc::new(BufStorage::<64>::new()));
+        let mut prod = queue.frame_producer();
+        assert!(commit_staged::<64, u64>(&[0; 32], &mut prod).is_ok());
+        assert!(matches!(
+            commit_staged::<64, u64>(&[0; 64], &mut prod),
+            Err(Error::BufferOverflow)
+        ));
     }
 }
@@ -1,40 +1,84 @@
 use std::{
     marker::PhantomData,
+    mem::ManuallyDrop,
     ops::{Deref, DerefMut},
+    sync::{
+        mpsc::{TryRecvError, TrySendError},
+        Arc, Mutex,
+    },
+    task::{Poll, Wake, Waker},
+    thread::{self, Thread},
 };
 
 use multiqueue::{BroadcastReceiver, BroadcastSender};
-use piton::{BufR, BufW, Error, Yule};
+use piton::{BufR, BufW, Envelope, Error, Shutdown, Yule};
 
+/// Creates a bus. Once every [`BusTx`] has been dropped, [`BusRx`]s receive what is left
+/// and then report that the bus is closed.
 pub fn pair<Msg: Yule>(capacity: u64) -> (BusTx<Msg>, BusRx<Msg>) {
     let (tx, rx) = multiqueue::broadcast_queue(capacity);
+    let wakers = Arc::<Wakers>::default();
     (
         BusTx {
-            tx,
+            tx: ManuallyDrop::new(tx),
+            wakers: wakers.clone(),
             _phantom: PhantomData,
         },
         BusRx {
             rx,
+            wakers,
             _phantom: PhantomData,
         },
     )
 }
 
+/// The wakers of the [`BusRx`]s waiting on a bus without blocking in `recv`, woken when a message is sent or a [`BusTx`] is dropped
+#[derive(Default)]
+struct Wakers(Mutex<Vec<Waker>>);
+
+impl Wakers {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.0.lock().expect("wakers poisoned");
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    fn wake(&self) {
+        for waker in self.0.lock().expect("wakers poisoned").drain(..) {
+            waker.wake();
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct BusTx<Msg: Yule> {
-    tx: BroadcastSender<Buf<Msg>>,
+    tx: ManuallyDrop<BroadcastSender<Buf<Msg>>>,
+    wakers: Arc<Wakers>,
     _phantom: PhantomData<Msg>,
 }
 
-impl<T: Yule> piton::BusTx for BusTx<T> {
+impl<Msg: Yule> Drop for BusTx<Msg> {
+    fn drop(&mut self) {
+        // The last sender closes the bus, which waiting receivers have to find out about.
+        // The sender has to be gone before they're woken, or they could see the bus still open.
+        // Safety: `tx` isn't used again
+        unsafe { ManuallyDrop::drop(&mut self.tx) };
+        self.wakers.wake();
+    }
+}
+
+impl<T: Envelope> piton::BusTx for BusTx<T> {
     type Msg = T;
     type BufW<'r> = Buf<T>;
 
     fn send(&'_ mut self, msg: Self::BufW<'_>) -> Result<(), Error> {
-        self.tx.try_send(msg).map_err(|e| {
-            println!("{:?}", e);
-            Error::TxFail
-        })
+        self.tx.try_send(msg).map_err(|e| match e {
+            TrySendError::Disconnected(_) => Error::Closed,
+            TrySendError::Full(_) => Error::TxFail,
+        })?;
+        self.wakers.wake();
+        Ok(())
     }
 
     fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error> {
@@ -42,8 +86,22 @@ impl<T: Yule> piton::BusTx for BusTx<T> {
     }
 }
 
+impl<T: Envelope> piton::AsyncBusTx for BusTx<T> {
+    type Msg = T;
+    type BufW<'r> = Buf<T>;
+
+    async fn send(&'_ mut self, msg: Self::BufW<'_>) -> Result<(), Error> {
+        piton::BusTx::send(self, msg)
+    }
+
+    async fn alloc<'r>(&mut self) -> Result<Self::BufW<'r>, Error> {
+        Ok(Buf(T::default()))
+    }
+}
+
 pub struct BusRx<Msg: Yule> {
     rx: BroadcastReceiver<Buf<Msg>>,
+    wakers: Arc<Wakers>,
     _phantom: PhantomData<Msg>,
 }
 
@@ -51,18 +109,79 @@ impl<Msg: Yule> Clone for BusRx<Msg> {
     fn clone(&self) -> Self {
         Self {
             rx: self.rx.add_stream(),
+            wakers: self.wakers.clone(),
             _phantom: PhantomData,
         }
     }
 }
 
-impl<Msg: Yule> piton::BusRx for BusRx<Msg> {
+impl<Msg: Envelope> piton::BusRx for BusRx<Msg> {
     type Msg = Msg;
     type BufR<'r> = Buf<Msg>;
 
     fn recv(&mut self) -> Result<Option<Self::BufR<'_>>, Error> {
-        let buf = self.rx.recv().map_err(|_| Error::RxFail)?;
-        Ok(Some(buf))
+        // `recv` only fails once every sender has been dropped
+        Ok(self.rx.recv().ok())
+    }
+
+    fn try_recv(&mut self) -> Result<Option<Self::BufR<'_>>, Error> {
+        match self.rx.try_recv() {
+            Ok(buf) => Ok(Some(buf)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(Error::Closed),
+        }
+    }
+
+    /// Parks the thread between messages, registering it to be unparked by the next send or by the shutdown
+    fn recv_until(&mut self, shutdown: &Shutdown) -> Result<Option<Self::BufR<'_>>, Error> {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let _registered = shutdown.register(&waker);
+        loop {
+            if shutdown.is_shutdown() {
+                return Ok(None);
+            }
+            if let Ok(buf) = self.rx.try_recv() {
+                return Ok(Some(buf));
+            }
+            self.wakers.register(&waker);
+            // A message may have been sent before the waker was registered
+            match self.rx.try_recv() {
+                Ok(buf) => return Ok(Some(buf)),
+                Err(TryRecvError::Empty) => thread::park(),
+                Err(TryRecvError::Disconnected) => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Wakes a thread parked in [`piton::BusRx::recv_until`]
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+impl<Msg: Envelope> piton::AsyncBusRx for BusRx<Msg> {
+    type Msg = Msg;
+    type BufR<'r> = Buf<Msg>;
+
+    /// Waits for a message, registering the task to be woken by the next send
+    async fn recv(&mut self) -> Result<Option<Self::BufR<'_>>, Error> {
+        std::future::poll_fn(|cx| {
+            if let Ok(buf) = self.rx.try_recv() {
+                return Poll::Ready(Ok(Some(buf)));
+            }
+            self.wakers.register(cx.waker());
+            // A message may have been sent before the waker was registered
+            match self.rx.try_recv() {
+                Ok(buf) => Poll::Ready(Ok(Some(buf))),
+                Err(TryRecvError::Empty) => Poll::Pending,
+                Err(TryRecvError::Disconnected) => Poll::Ready(Ok(None)),
+            }
+        })
+        .await
     }
 }
 
@@ -75,9 +194,14 @@ impl<T: Yule> BufW<'_, T> for Buf<T> {
     }
 }
 
-impl<T: Yule> BufR<'_, T> for Buf<T> {
-    fn as_ref(&self) -> &T {
-        &self.0
+impl<T: Envelope> BufR<'_, T> for Buf<T> {
+    fn frame(&self) -> &[u8] {
+        &self.0.as_slice()[..self.0.frame_len()]
+    }
+
+    fn view(&self) -> T::Ref<'_> {
+        // Safety: the frame is the variant in use of a `T`, so it is always valid
+        unsafe { T::view_unchecked(self.frame()) }
     }
 }
 